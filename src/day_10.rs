@@ -8,6 +8,7 @@ pub fn run(part: i32) {
     println!("Result is {}", result);
 }
 
+#[allow(clippy::needless_range_loop)]
 fn from_text(input: &str, _part: u32) -> usize {
     println!("Input: ");
     println!("{}", input);
@@ -76,7 +77,7 @@ mod tests {
 
         let expected = 8;
 
-        assert_eq!(from_text(&input, 1), expected);
+        assert_eq!(from_text(input, 1), expected);
     }
 
     #[test]
@@ -95,7 +96,7 @@ mod tests {
 
         let expected = 33;
 
-        assert_eq!(from_text(&input, 1), expected);
+        assert_eq!(from_text(input, 1), expected);
     }
 
     #[test]
@@ -114,7 +115,7 @@ mod tests {
 
         let expected = 35;
 
-        assert_eq!(from_text(&input, 1), expected);
+        assert_eq!(from_text(input, 1), expected);
     }
 
     #[test]
@@ -133,7 +134,7 @@ mod tests {
 
         let expected = 41;
 
-        assert_eq!(from_text(&input, 1), expected);
+        assert_eq!(from_text(input, 1), expected);
     }
 
     #[test]
@@ -162,6 +163,6 @@ mod tests {
 
         let expected = 210;
 
-        assert_eq!(from_text(&input, 1), expected);
+        assert_eq!(from_text(input, 1), expected);
     }
 }
\ No newline at end of file
@@ -11,7 +11,7 @@ pub fn run(part: i32) {
 }
 
 fn with_input(input: &str, part: u32) -> i32 {
-    let orbit_map = orbit_map(&input);
+    let orbit_map = orbit_map(input);
 
     if part == 1 {
         return calculate_total_orbits(&orbit_map) as i32;
@@ -35,7 +35,7 @@ fn calculate_total_orbits(orbit_map: &HashMap<&str, &str>) -> usize {
     let mut count = 0;
     let keys: Vec<&&str> = orbit_map.keys().collect();
     for key in keys {
-        let orbits = count_orbits(key, &orbit_map);
+        let orbits = count_orbits(key, orbit_map);
         count += orbits;
     }
 
@@ -43,8 +43,8 @@ fn calculate_total_orbits(orbit_map: &HashMap<&str, &str>) -> usize {
 }
 
 fn calculate_path_to_santa(orbit_map: &HashMap<&str, &str>) -> usize {
-    let you_orbits = orbits("YOU", &orbit_map);
-    let san_orbits = orbits("SAN", &orbit_map);
+    let you_orbits = orbits("YOU", orbit_map);
+    let san_orbits = orbits("SAN", orbit_map);
 
     
     for (index_you, value_you) in you_orbits.iter().enumerate() {
@@ -1,4 +1,6 @@
-use std::mem;
+use aoc2019::intcode;
+use aoc2019::intcode::IntCodeComputer;
+use aoc2019::intcode::network::{Network, NetworkState};
 
 use super::file_loader;
 
@@ -8,10 +10,8 @@ pub fn run(part: i32) {
     println!("Result is {}", result);
 }
 
-fn calculate_highest_thruster_signal(input: &str, part: u32) -> i32 {
-    let intcode: Vec<i32> = input.split(",")
-        .map(|number| number.parse::<i32>().unwrap())
-        .collect();
+fn calculate_highest_thruster_signal(input: &str, part: u32) -> i64 {
+    let intcode: Vec<i64> = intcode::parse(input);
 
     let low = if part == 1 {
         0
@@ -60,255 +60,25 @@ fn build_sequence(sequences: &mut Vec<Vec<u32>>, taken: Vec<u32>, low: u32, high
     }
 }
 
-fn signal_from_sequence(intcode: &Vec<i32>, sequence: Vec<u32>, recusive: bool) -> i32 {
-    let mut signal = 0;
-    let mut amplifiers: Vec<IntCodeComputer> = vec![];
-    let debug = false;
-
-    for phase_setting in &sequence {
-        amplifiers.push(IntCodeComputer {
-            intcode: intcode.to_vec(),
-            inputs: vec![*phase_setting as i32],
-            outputs: vec![],
-            ptr: 0,
-            debug: debug,
-        });
-    }
+fn signal_from_sequence(intcode: &[i64], sequence: Vec<u32>, recusive: bool) -> i64 {
+    let amplifiers: Vec<IntCodeComputer> = sequence.iter()
+        .map(|phase_setting| IntCodeComputer::new(intcode, vec![*phase_setting as i64]))
+        .collect();
 
-    if recusive {
-        let mut count = 0;
-        loop {
-            let index = count % amplifiers.len();
-            let amp: &mut IntCodeComputer = &mut amplifiers[index];
-            amp.inputs.push(signal);
-            let result = amp.run();
-            let output = amp.outputs.remove(0);
-            if debug {
-                println!("\nAmp: {}, ps {}, so {}, out: {}", index, sequence[index], signal, output);
-            }
-            signal = output;
-            count += 1;
-
-            if result == HALT && index == amplifiers.len() - 1 {
-                break;
-            }
-        }
+    let mut network = if recusive {
+        Network::ring(amplifiers)
     } else {
-        for (index, ps) in sequence.iter().enumerate() {
-            let amp: &mut IntCodeComputer = &mut amplifiers[index];
-            amp.inputs.push(signal);
-            amp.run();
-            let output = amp.outputs.remove(0);
-            signal = output;
-            if debug {
-                println!("\nAmp: {}, ps {}, so {}, out: {}", index, ps, signal, output);
-            }
-        }
-    }
-
-    return signal;
-}
-
-pub struct IntCodeComputer {
-    pub intcode: Vec<i32>,
-    inputs: Vec<i32>,
-    outputs: Vec<i32>,
-    ptr: usize,
-    debug: bool,
-}
-
-const NEED_INPUT: u32 = 2;
-const HALT: u32 = 1;
-const RESULT_OK: u32 = 0;
-impl IntCodeComputer {
-
-    pub fn run(&mut self) -> u32 {
-        if self.debug {
-            println!("\nRunning intcode: {:?}", self.intcode);
-        }
-        loop {
-            let result = self.next_operation();
-            if result != RESULT_OK {
-                return result;
-            }
-        }
-    }
-
-    fn next_operation(&mut self) -> u32 {
-        let opcode = self.intcode[self.ptr];
-        let operation = self.operation_from_opcode(opcode);
-        
-        match operation {
-            1 => self.add(opcode),
-            2 => self.multiply(opcode),
-            3 => {
-                let result = self.input(opcode);
-                if result == NEED_INPUT {
-                    // halt until input
-                    return NEED_INPUT;
-                }
-            },
-            4 => self.output(opcode),
-            5 => self.jump_if_true(opcode),
-            6 => self.jump_if_false(opcode),
-            7 => self.less_then(opcode),
-            8 => self.equals(opcode),
-            99 => return HALT,
-            _ => panic!("Unknown opcode {}. Something went wrong", opcode)
-        }
-
-        if self.debug {
-            println!("--State: ptr: {}. Intcode:", self.ptr);
-            print!("--");
-            for (index, item) in self.intcode.iter().enumerate() {
-                print!("[{}: {}], ", index, item);
-            }
-            print!("\n");
-        }
-    
-        return RESULT_OK;
-    }
+        Network::pipeline(amplifiers)
+    };
+    network.send(0, 0);
 
-    fn operation_from_opcode(&self, opcode: i32) -> i32 {
-        return opcode % 100;
+    let state = network.run().expect("Amplifier program faulted");
+    if state != NetworkState::Halted {
+        panic!("Amplifiers stopped before halting: {:?}", state);
     }
 
-    fn add(&mut self, opcode: i32) {
-        let parameter_modes = self.modes_from_opcode(opcode, 3);
-        let value1: i32 = self.value_from_parameter(parameter_modes[0], &self.intcode, self.ptr + 1);
-        let value2: i32 = self.value_from_parameter(parameter_modes[1], &self.intcode, self.ptr + 2);
-        let result_address: usize = self.intcode[self.ptr + 3] as usize;
-        let result: i32 = value1 + value2;
-        if self.debug {
-            println!("--Adding {} and {} to get {} to put at index {}", value1, value2, result, result_address);
-        }
-        mem::replace(&mut self.intcode[result_address], result);
-        self.ptr += 4;
-    }
-    
-    fn multiply(&mut self, opcode: i32) {
-        let parameter_modes = self.modes_from_opcode(opcode, 3);
-        let value1: i32 = self.value_from_parameter(parameter_modes[0], &self.intcode, self.ptr + 1);
-        let value2: i32 = self.value_from_parameter(parameter_modes[1], &self.intcode, self.ptr + 2);
-        let result_address: usize = self.intcode[self.ptr + 3] as usize;
-        let result: i32 = value1 * value2;
-        if self.debug {
-            println!("--Multiplying {} and {} to get {} to put at index {}", value1, value2, result, result_address);
-        }
-        mem::replace(&mut self.intcode[result_address], result);
-        self.ptr += 4;
-    }
-    
-    fn input(&mut self, _opcode: i32) -> u32 {
-        let result_address: usize = self.intcode[self.ptr + 1] as usize;
-        if self.inputs.is_empty() {
-            if self.debug {
-                println!("--Need Input for address {}. Waiting...", result_address);
-            }
-            return NEED_INPUT;
-        }
-        let input = self.inputs.remove(0);
-        if self.debug {
-            println!("--Inputting {} to address {}", input, result_address);
-        }
-        mem::replace(&mut self.intcode[result_address], input);
-        self.ptr += 2;
-        return 0;
-    }
-    
-    fn output(&mut self, opcode: i32) {
-        let parameter_modes = self.modes_from_opcode(opcode, 1);
-        let output = self.value_from_parameter(parameter_modes[0], &self.intcode, self.ptr + 1);
-        if self.debug {
-            println!("--Outputting {} from address {}", output, self.intcode[self.ptr + 1]);
-        }
-        self.outputs.push(output);
-        self.ptr += 2;
-    }
-    
-    fn jump_if_true(&mut self, opcode: i32) {
-        let parameter_modes = self.modes_from_opcode(opcode, 2);
-        let value1: i32 = self.value_from_parameter(parameter_modes[0], &self.intcode, self.ptr + 1);
-        let value2: i32 = self.value_from_parameter(parameter_modes[1], &self.intcode, self.ptr + 2);
-        if self.debug {
-            println!("--Jump if value1 != 0: Value1: {}, Value2(jump): {}", value1, value2);
-        }
-        if value1 != 0 {
-            self.ptr = value2 as usize;
-        } else {
-            self.ptr += 3;
-        }
-    }
-    
-    fn jump_if_false(&mut self, opcode: i32) {
-        let parameter_modes = self.modes_from_opcode(opcode, 2);
-        let value1: i32 = self.value_from_parameter(parameter_modes[0], &self.intcode, self.ptr + 1);
-        let value2: i32 = self.value_from_parameter(parameter_modes[1], &self.intcode, self.ptr + 2);
-        if self.debug {
-            println!("--Jump if value1 == 0: Value1: {}, Value2: {}", value1, value2);
-        }
-        if value1 == 0 {
-            self.ptr = value2 as usize;
-        } else {
-            self.ptr += 3;
-        }
-    }
-    
-    fn less_then(&mut self, opcode: i32) {
-        let parameter_modes = self.modes_from_opcode(opcode, 3);
-        let value1: i32 = self.value_from_parameter(parameter_modes[0], &self.intcode, self.ptr + 1);
-        let value2: i32 = self.value_from_parameter(parameter_modes[1], &self.intcode, self.ptr + 2);
-        let value3: usize = self.intcode[self.ptr + 3] as usize;
-        if self.debug {
-            println!("--Less than: Value1 {}, Value2: {}, Value3: {}", value1, value2, value3);
-        }
-        if value1 < value2 {
-            mem::replace(&mut self.intcode[value3], 1);
-        } else {
-            mem::replace(&mut self.intcode[value3], 0);
-        }
-        self.ptr += 4;
-    }
-    
-    fn equals(&mut self, opcode: i32) {
-        let parameter_modes = self.modes_from_opcode(opcode, 3);
-        let value1: i32 = self.value_from_parameter(parameter_modes[0], &self.intcode, self.ptr + 1);
-        let value2: i32 = self.value_from_parameter(parameter_modes[1], &self.intcode, self.ptr + 2);
-        let value3: usize = self.intcode[self.ptr + 3] as usize;
-        if self.debug {
-            println!("--Equals: Value1 {}, Value2: {}, Value 3: {}", value1, value2, value3);
-        }
-        if value1 == value2 {
-            mem::replace(&mut self.intcode[value3], 1);
-        } else {
-            mem::replace(&mut self.intcode[value3], 0);
-        }
-        self.ptr += 4;
-    }
-    
-    fn modes_from_opcode(&self, opcode: i32, parameters: usize) -> Vec<i32> {
-        let params = opcode / 100;
-        let mut results = vec![];
-        let base: i32 = 10;
-        for i in 0..parameters {
-            let power = (i + 1) as u32;
-            results.push((params % base.pow(power)) / base.pow(power-1));
-        }
-    
-        return results;
-    }
-    
-    fn value_from_parameter(&self, parameter: i32, intcode: &Vec<i32>, ptr: usize) -> i32 {
-        return match parameter {
-            0 => {
-                let value_at_pointer = intcode[ptr];
-                assert!(value_at_pointer >= 0);
-                intcode[value_at_pointer as usize]
-            },
-            1 => intcode[ptr],
-            _ => panic!("parameter value {} not understood", parameter)
-        };
-    }
+    let last = network.len() - 1;
+    return *network.outputs(last).last().expect("Last amplifier gave no signal");
 }
 
 #[cfg(test)]
@@ -24,13 +24,13 @@ fn result_for_part(input: &str, part: u32) -> i32 {
     }
 }
 
-fn part_two(layered_image: &Vec<Vec<Vec<u32>>>) -> i32 {
+fn part_two(layered_image: &[Vec<Vec<u32>>]) -> i32 {
     let image = resolve_layers(layered_image);
     display_image(&image);
     return 0;
 }
 
-fn part_one(image: &Vec<Vec<Vec<u32>>>) -> i32 {
+fn part_one(image: &[Vec<Vec<u32>>]) -> i32 {
     let mut fewest_zero_digits = -1;
     let mut fewest_zero_digits_layer = 0;
 
@@ -65,8 +65,8 @@ fn part_one(image: &Vec<Vec<Vec<u32>>>) -> i32 {
     return ones * twos;
 }
 
-fn resolve_layers(layered_image: &Vec<Vec<Vec<u32>>>) -> Vec<Vec<u32>> {
-    let mut image: Vec<Vec<u32>> = layered_image.get(0).unwrap().to_vec();
+fn resolve_layers(layered_image: &[Vec<Vec<u32>>]) -> Vec<Vec<u32>> {
+    let mut image: Vec<Vec<u32>> = layered_image.first().unwrap().to_vec();
 
     for layer in layered_image {
         for (row_index, row) in layer.iter().enumerate() {
@@ -86,7 +86,7 @@ fn resolve_layers(layered_image: &Vec<Vec<Vec<u32>>>) -> Vec<Vec<u32>> {
 fn display_image(image: &Vec<Vec<u32>>) {
     println!("\nImage:");
     for row in image {
-        let row_out = row.iter().fold("".to_owned(), |pixel_string, pixel| fold_pixels_to_string(pixel_string, pixel));
+        let row_out = row.iter().fold("".to_owned(), fold_pixels_to_string);
         println!("{}", row_out);
     }
 
@@ -98,12 +98,12 @@ fn fold_pixels_to_string(pixel_string: String, pixel: &u32) -> String {
         0 => ' ',
         1 => 'W',
         2 => 'T',
-        _ => panic!("\nPixel {} not understood\n")
+        _ => panic!("\nPixel {} not understood\n", pixel)
     };
     return format!("{}{}", pixel_string, character);
 }
 
-fn make_layers(data: &Vec<u32>, width: usize, height: usize) -> Vec<Vec<Vec<u32>>> {
+fn make_layers(data: &[u32], width: usize, height: usize) -> Vec<Vec<Vec<u32>>> {
     let mut image: Vec<Vec<Vec<u32>>> = Vec::new();
     let mut ptr = 0;
 
@@ -120,7 +120,7 @@ fn make_layers(data: &Vec<u32>, width: usize, height: usize) -> Vec<Vec<Vec<u32>
         }
 
         image.push(layer);
-        if data.get(ptr) == None {
+        if data.get(ptr).is_none() {
             println!("We have {} layers", image.len());
             break;
         }
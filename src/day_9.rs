@@ -1,27 +1,24 @@
-use super::file_loader;
-
-mod day_9_computer;
+use aoc2019::intcode;
+use aoc2019::intcode::IntCodeComputer;
 
-use day_9_computer::IntCodeComputer;
+use super::file_loader;
 
 pub fn run(part: i32) {
     let input = file_loader::load_file("9.input");
-    let intcode: Vec<i64> = input.split(",")
-        .map(|number| number.parse::<i64>().unwrap())
-        .collect();
+    let intcode: Vec<i64> = intcode::parse(&input);
 
     let result = result_for_part(&intcode, part as u32);
     println!("Result is {}", result);
 }
 
-fn result_for_part(intcode: &Vec<i64>, part: u32) -> i64 {
+fn result_for_part(intcode: &[i64], part: u32) -> i64 {
     let inputs = if part == 1 {
         vec![1]
     } else {
         vec![2]
     };
     let mut computer = IntCodeComputer::new(intcode, inputs);
-    computer.run();
+    computer.run().expect("BOOST program faulted");
     println!("\nComputer outputs:");
     let mut last_index: i64 = -1;
     for op in &computer.outputs {
@@ -29,4 +26,4 @@ fn result_for_part(intcode: &Vec<i64>, part: u32) -> i64 {
         println!("{}", op);
     }
     return computer.outputs[last_index as usize];
-}
\ No newline at end of file
+}
@@ -62,15 +62,15 @@ mod tests {
     
     #[test]
     fn test_match_criteria() {
-        assert_eq!(match_criteria(223450, 1), false);
-        assert_eq!(match_criteria(111111, 1), true);
-        assert_eq!(match_criteria(123789, 1), false);
+        assert!(!match_criteria(223450, 1));
+        assert!(match_criteria(111111, 1));
+        assert!(!match_criteria(123789, 1));
     }
 
     #[test]
     fn test_match_criteria_part_2() {
-        assert_eq!(match_criteria(112233, 2), true);
-        assert_eq!(match_criteria(123444, 2), false);
-        assert_eq!(match_criteria(111122, 2), true);
+        assert!(match_criteria(112233, 2));
+        assert!(!match_criteria(123444, 2));
+        assert!(match_criteria(111122, 2));
     }
 }
\ No newline at end of file
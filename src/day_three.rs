@@ -56,7 +56,7 @@ fn with_input(input: &str, part: u32) -> i32 {
         //println!("Intersections: {:?}", intersections);
         let manhatten_distance: i32 = intersections
             .map(|pp| (pp.0.abs(), pp.1.abs()))
-            .map(|pp| (pp.0 + pp.1))
+            .map(|pp| pp.0 + pp.1)
             .min()
             .unwrap();
     
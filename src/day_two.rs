@@ -1,28 +1,45 @@
-use std::mem;
 use std::env;
 
+use aoc2019::intcode;
+use aoc2019::intcode::{Budget, IntCodeComputer, IntcodeError, RunState};
+use aoc2019::intcode::opcodes::{Dialect, OpcodeTable};
+
 use super::file_loader;
 
+/// Far more than the real program needs; a guess that runs longer is stuck.
+const STEP_BUDGET: u64 = 100_000;
+
 pub fn run(part: i32) {
     let input = file_loader::load_file("2.input");
     println!("File content: {:?}", input);
 
-    let intcode: Vec<u32> = input.split(",")
-        .map(|number| number.parse::<u32>().unwrap())
-        .collect();
+    let intcode: Vec<i64> = intcode::parse(&input);
 
     if part == 1 {
         let args: Vec<String> = env::args().collect();
-        let noun = args[3].parse::<u32>().expect("Should provide noun");
-        let verb = args[4].parse::<u32>().expect("Should provide verb");
+        let noun = args[3].parse::<i64>().expect("Should provide noun");
+        let verb = args[4].parse::<i64>().expect("Should provide verb");
 
-        let result = process_for_result(intcode.to_vec(), noun, verb);
+        let result = process_for_result(&intcode, noun, verb).expect("Gravity assist program faulted");
         println!();
-        println!("Answer is {}", result);
+        match result {
+            Some(result) => println!("Answer is {}", result),
+            None => println!("Program did not halt within {} steps", STEP_BUDGET),
+        }
     } else {
         'outer: for i in 30..60 {
-            'inner: for j in 30..50 {
-                let result = process_for_result(intcode.to_vec(), i, j);
+            for j in 30..50 {
+                let result = match process_for_result(&intcode, i, j) {
+                    Ok(Some(result)) => result,
+                    Ok(None) => {
+                        println!("Noun {} and verb {} did not halt within {} steps", i, j, STEP_BUDGET);
+                        continue;
+                    },
+                    Err(error) => {
+                        println!("Noun {} and verb {} faulted: {}", i, j, error);
+                        continue;
+                    }
+                };
                 println!("Result for n {} and v {} is {}", i, j, result);
                 if result == 19690720 {
                     println!();
@@ -35,56 +52,16 @@ pub fn run(part: i32) {
     
 }
 
-fn process_for_result(mut intcode: Vec<u32>, noun: u32, verb: u32) -> u32 {
-    //println!("Processing: {:?}", intcode);
-    intcode[1] = noun;
-    intcode[2] = verb;
-
-    let mut ptr: usize = 0;
-    loop {
-        //println!("Pointer is: {}, Value is: {}", ptr, intcode[ptr]);
-        //println!("Before: {:?}", intcode);
-        process_next_number(&mut intcode, &mut ptr);
-        if intcode[ptr] == 99 {
-            break;
-        }
-    }
-
-    //println!("Final: {:?}", intcode);
-
-    return intcode[0];
-}
+fn process_for_result(intcode: &[i64], noun: i64, verb: i64) -> Result<Option<i64>, IntcodeError> {
+    let mut computer = IntCodeComputer::new(intcode, vec![]);
+    computer.set_opcodes(OpcodeTable::dialect(Dialect::Day2));
+    computer.poke(1, noun)?;
+    computer.poke(2, verb)?;
+    computer.set_budget(Budget::steps(STEP_BUDGET));
 
-fn process_next_number(run: &mut Vec<u32>, ptr: &mut usize) {
-    let opcode = run[*ptr];
-    
-    match opcode {
-        1 => add(run, ptr),
-        2 => multiply(run, ptr),
-        _ => panic!("Unknown opcode {}. Something went wrong", opcode)
+    if computer.run()? == RunState::BudgetExhausted {
+        return Ok(None);
     }
-}
 
-fn add(run: &mut Vec<u32>, ptr: &mut usize) {
-    let one: usize = run[*ptr + 1] as usize;
-    let two: usize = run[*ptr + 2] as usize;
-    let answer: usize = run[*ptr + 3] as usize;
-    let result = run[one] + run[two];
-    //println!("Adding {} and {} to get {} to put at index {}", run[one], run[two], result, answer);
-    //run.insert(answer, result);
-    mem::replace(&mut run[answer], result);
-    //let mut newValue: usize = *ptr + 4;
-    *ptr = *ptr + 4;
+    return Ok(Some(computer.peek(0)));
 }
-
-fn multiply(run: &mut Vec<u32>, ptr: &mut usize) {
-    let one: usize = run[*ptr + 1] as usize;
-    let two: usize = run[*ptr + 2] as usize;
-    let answer: usize = run[*ptr + 3] as usize;
-    let result = run[one] * run[two];
-    //println!("Adding {} and {} to get {} to put at index {}", run[one], run[two], result, answer);
-    //run.insert(answer, result);
-    mem::replace(&mut run[answer], result);
-    //let mut newValue: usize = *ptr + 4;
-    *ptr = *ptr + 4;
-}
\ No newline at end of file
@@ -0,0 +1,49 @@
+mod budget;
+mod cell;
+mod computer;
+mod decode;
+mod error;
+mod history;
+mod io;
+mod rng;
+pub mod assembler;
+pub mod bigint;
+pub mod cfg;
+pub mod compiled;
+pub mod coverage;
+pub mod debugger;
+pub mod devices;
+pub mod differential;
+pub mod disassembler;
+pub mod fuzz;
+pub mod host;
+pub mod memory;
+pub mod network;
+pub mod opcodes;
+pub mod profiler;
+pub mod protection;
+pub mod snapshot;
+pub mod threaded;
+pub mod trace;
+
+pub use budget::Budget;
+pub use cell::{Cell, CheckedI64};
+pub use computer::{IntCodeComputer, RunState};
+pub use computer::{operation_from_opcode, modes_from_opcode};
+pub use decode::Instruction;
+pub use error::{IntcodeError, ErrorReason};
+pub use history::UndoRecord;
+pub use assembler::{assemble, AssembleError};
+pub use disassembler::{disassemble, listing};
+pub use memory::{Memory, PagedMemory};
+pub use io::{InputSource, OutputSink, FnInput, FnOutput, StdinInput, StdoutOutput};
+
+pub fn parse(input: &str) -> Vec<i64> {
+    return parse_cells(input);
+}
+
+pub fn parse_cells<C: Cell>(input: &str) -> Vec<C> {
+    return input.trim().split(',')
+        .map(|number| number.trim().parse::<C>().unwrap_or_else(|_| panic!("'{}' is not a number", number.trim())))
+        .collect();
+}
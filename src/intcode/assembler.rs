@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use super::disassembler::{encode, instruction_info, operation_from_mnemonic};
+
+/// An assembly failure. Line and column are 1-based.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AssembleError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl AssembleError {
+    fn new(line: usize, column: usize, message: String) -> AssembleError {
+        return AssembleError { line, column, message };
+    }
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}:{}: {}", self.line, self.column, self.message);
+    }
+}
+
+impl Error for AssembleError {}
+
+#[derive(Debug, Clone)]
+enum Value {
+    Number(i64),
+    Label(String),
+}
+
+#[derive(Debug, Clone)]
+struct Operand {
+    mode: i64,
+    value: Value,
+    column: usize,
+}
+
+#[derive(Debug)]
+struct Statement {
+    line: usize,
+    opcode: Option<i64>,
+    operands: Vec<Operand>,
+}
+
+/// Assembles source in the disassembler's listing format back into intcode.
+///
+/// Each line holds optional `label:` definitions followed by an instruction
+/// (`ADD a, #b, @c`) or a `.data` directive with comma separated values.
+/// Operands may be numbers or labels, with `#` for immediate and `@` for
+/// relative mode. Numeric labels such as the `12:` addresses in a listing are
+/// ignored, and `;` starts a comment.
+pub fn assemble(source: &str) -> Result<Vec<i64>, AssembleError> {
+    let mut labels: HashMap<String, i64> = HashMap::new();
+    let mut statements: Vec<Statement> = vec![];
+    let mut address: i64 = 0;
+
+    for (index, text) in source.lines().enumerate() {
+        let line = index + 1;
+        let code = match text.find(';') {
+            Some(comment) => &text[..comment],
+            None => text,
+        };
+
+        let mut rest = code;
+        let mut column = 1;
+        let (word, word_column, after) = loop {
+            let (word, word_column, after) = next_word(rest, column);
+            if word.is_empty() || !after.starts_with(':') {
+                break (word, word_column, after);
+            }
+
+            if !word.chars().all(|c| c.is_ascii_digit()) {
+                if !is_identifier(word) {
+                    return Err(AssembleError::new(line, word_column, format!("invalid label '{}'", word)));
+                }
+                if labels.insert(word.to_owned(), address).is_some() {
+                    return Err(AssembleError::new(line, word_column, format!("label '{}' is already defined", word)));
+                }
+            }
+            column = word_column + word.len() + 1;
+            rest = &after[1..];
+        };
+
+        if word.is_empty() {
+            if !after.is_empty() {
+                return Err(AssembleError::new(line, word_column, "expected a mnemonic".to_owned()));
+            }
+            continue;
+        }
+        let operands = parse_operands(after, word_column + word.len(), line)?;
+
+        if word.eq_ignore_ascii_case(".data") {
+            for operand in &operands {
+                if operand.mode != 0 {
+                    return Err(AssembleError::new(line, operand.column, "data values take no mode".to_owned()));
+                }
+            }
+            address += operands.len() as i64;
+            statements.push(Statement { line, opcode: None, operands });
+            continue;
+        }
+
+        let operation = match operation_from_mnemonic(word) {
+            Some(operation) => operation,
+            None => return Err(AssembleError::new(line, word_column, format!("unknown mnemonic '{}'", word))),
+        };
+        let (mnemonic, parameters) = instruction_info(operation).unwrap();
+        if operands.len() != parameters {
+            let message = format!("{} takes {} operands but {} were given", mnemonic, parameters, operands.len());
+            return Err(AssembleError::new(line, word_column, message));
+        }
+
+        let modes: Vec<i64> = operands.iter().map(|operand| operand.mode).collect();
+        address += parameters as i64 + 1;
+        statements.push(Statement { line, opcode: Some(encode(operation, &modes)), operands });
+    }
+
+    let mut intcode = vec![];
+    for statement in statements {
+        if let Some(opcode) = statement.opcode {
+            intcode.push(opcode);
+        }
+        for operand in statement.operands {
+            intcode.push(match operand.value {
+                Value::Number(number) => number,
+                Value::Label(label) => match labels.get(&label) {
+                    Some(address) => *address,
+                    None => return Err(AssembleError::new(statement.line, operand.column, format!("undefined label '{}'", label))),
+                },
+            });
+        }
+    }
+
+    return Ok(intcode);
+}
+
+/// Splits off the next word, returning it with its column and the remaining text.
+fn next_word(text: &str, column: usize) -> (&str, usize, &str) {
+    let trimmed = text.trim_start();
+    let word_column = column + text.len() - trimmed.len();
+    let end = trimmed.find(|c: char| c.is_whitespace() || c == ':' || c == ',')
+        .unwrap_or(trimmed.len());
+    return (&trimmed[..end], word_column, trimmed[end..].trim_end());
+}
+
+fn parse_operands(text: &str, column: usize, line: usize) -> Result<Vec<Operand>, AssembleError> {
+    let mut operands = vec![];
+    if text.trim().is_empty() {
+        return Ok(operands);
+    }
+
+    let mut column = column;
+    for part in text.split(',') {
+        let trimmed = part.trim();
+        let start = column + part.len() - part.trim_start().len();
+        if trimmed.is_empty() {
+            return Err(AssembleError::new(line, start, "missing operand".to_owned()));
+        }
+
+        let (mode, token) = match trimmed.chars().next() {
+            Some('#') => (1, &trimmed[1..]),
+            Some('@') => (2, &trimmed[1..]),
+            _ => (0, trimmed),
+        };
+        let value = if let Ok(number) = token.parse::<i64>() {
+            Value::Number(number)
+        } else if is_identifier(token) {
+            Value::Label(token.to_owned())
+        } else {
+            return Err(AssembleError::new(line, start, format!("invalid operand '{}'", trimmed)));
+        };
+
+        operands.push(Operand { mode, value, column: start });
+        column += part.len() + 1;
+    }
+
+    return Ok(operands);
+}
+
+fn is_identifier(word: &str) -> bool {
+    let mut chars = word.chars();
+    return match chars.next() {
+        Some(first) => (first.is_ascii_alphabetic() || first == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        None => false,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::disassembler::listing;
+
+    #[test]
+    fn test_assemble_quine() {
+        let source = "
+            ; day 9 example 1: outputs a copy of itself
+            start:  ARB #1
+                    OUT @-1
+                    ADD 100, #1, 100
+                    EQ  100, #16, 101
+                    JF  101, #start
+                    HLT
+        ";
+        let expected = vec![109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99];
+
+        assert_eq!(assemble(source), Ok(expected));
+    }
+
+    #[test]
+    fn test_data_directive() {
+        let source = "JT #1, #end\n\
+                      table: .data 1, -2, table\n\
+                      end: HLT";
+
+        assert_eq!(assemble(source), Ok(vec![1105,1,6,1,-2,3,99]));
+    }
+
+    #[test]
+    fn test_round_trip_listing() {
+        for program in &[include_str!("../../resources/5.input"), include_str!("../../resources/9.input")] {
+            let intcode = super::super::parse(program);
+
+            assert_eq!(assemble(&listing(&intcode)), Ok(intcode));
+        }
+    }
+
+    #[test]
+    fn test_errors_have_positions() {
+        let cases = vec![
+            ("ADD 1, 2, 3\n  FOO 1", 2, 3, "unknown mnemonic 'FOO'"),
+            ("  MUL 1, 2", 1, 3, "MUL takes 3 operands but 2 were given"),
+            ("OUT  #missing", 1, 6, "undefined label 'missing'"),
+            ("a: HLT\na: HLT", 2, 1, "label 'a' is already defined"),
+            ("ADD 1, , 3", 1, 8, "missing operand"),
+            ("IN 4x", 1, 4, "invalid operand '4x'"),
+            (".data #1", 1, 7, "data values take no mode"),
+        ];
+
+        for (source, line, column, message) in cases {
+            let error = assemble(source).unwrap_err();
+            assert_eq!(error, AssembleError::new(line, column, message.to_owned()), "\nFailed on {:?}\n", source);
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use super::cell::Cell;
+
+const DECIMAL_BASE: u32 = 1_000_000_000;
+const DECIMAL_DIGITS: usize = 9;
+
+/// A signed integer of any size, for running programs whose values outgrow
+/// 64 bits. Arithmetic never overflows.
+#[derive(PartialEq, Eq, Clone, Hash, Default)]
+pub struct BigInt {
+    negative: bool,
+    /// Base 2^32 digits, least significant first, with no leading zeros.
+    /// Zero is empty and never negative.
+    magnitude: Vec<u32>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseBigIntError(String);
+
+impl BigInt {
+    fn new(negative: bool, mut magnitude: Vec<u32>) -> BigInt {
+        while magnitude.last() == Some(&0) {
+            magnitude.pop();
+        }
+        let negative = negative && !magnitude.is_empty();
+        return BigInt { negative, magnitude };
+    }
+
+    pub fn is_negative(&self) -> bool {
+        return self.negative;
+    }
+}
+
+fn compare_magnitudes(left: &[u32], right: &[u32]) -> Ordering {
+    if left.len() != right.len() {
+        return left.len().cmp(&right.len());
+    }
+    return left.iter().rev().cmp(right.iter().rev());
+}
+
+fn add_magnitudes(left: &[u32], right: &[u32]) -> Vec<u32> {
+    let mut sum = Vec::with_capacity(left.len().max(right.len()) + 1);
+    let mut carry = 0u64;
+    for index in 0..left.len().max(right.len()) {
+        let total = *left.get(index).unwrap_or(&0) as u64 + *right.get(index).unwrap_or(&0) as u64 + carry;
+        sum.push(total as u32);
+        carry = total >> 32;
+    }
+    sum.push(carry as u32);
+    return sum;
+}
+
+/// `larger` must be at least `smaller`.
+fn subtract_magnitudes(larger: &[u32], smaller: &[u32]) -> Vec<u32> {
+    let mut difference = Vec::with_capacity(larger.len());
+    let mut borrow = 0i64;
+    for (index, digit) in larger.iter().enumerate() {
+        let mut total = *digit as i64 - *smaller.get(index).unwrap_or(&0) as i64 - borrow;
+        borrow = 0;
+        if total < 0 {
+            total += 1 << 32;
+            borrow = 1;
+        }
+        difference.push(total as u32);
+    }
+    return difference;
+}
+
+fn multiply_magnitudes(left: &[u32], right: &[u32]) -> Vec<u32> {
+    let mut product = vec![0u32; left.len() + right.len()];
+    for (i, left_digit) in left.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, right_digit) in right.iter().enumerate() {
+            let total = *left_digit as u64 * *right_digit as u64 + product[i + j] as u64 + carry;
+            product[i + j] = total as u32;
+            carry = total >> 32;
+        }
+        product[i + right.len()] = carry as u32;
+    }
+    return product;
+}
+
+/// Multiplies in place by `factor` and adds `addend`.
+fn multiply_add_small(magnitude: &mut Vec<u32>, factor: u32, addend: u32) {
+    let mut carry = addend as u64;
+    for digit in magnitude.iter_mut() {
+        let total = *digit as u64 * factor as u64 + carry;
+        *digit = total as u32;
+        carry = total >> 32;
+    }
+    if carry > 0 {
+        magnitude.push(carry as u32);
+    }
+}
+
+/// Divides in place by `divisor`, returning the remainder.
+fn divide_small(magnitude: &mut Vec<u32>, divisor: u32) -> u32 {
+    let mut remainder = 0u64;
+    for digit in magnitude.iter_mut().rev() {
+        let total = (remainder << 32) | *digit as u64;
+        *digit = (total / divisor as u64) as u32;
+        remainder = total % divisor as u64;
+    }
+    while magnitude.last() == Some(&0) {
+        magnitude.pop();
+    }
+    return remainder as u32;
+}
+
+impl Cell for BigInt {
+    fn from_i64(value: i64) -> BigInt {
+        let magnitude = value.unsigned_abs();
+        return BigInt::new(value < 0, vec![magnitude as u32, (magnitude >> 32) as u32]);
+    }
+
+    fn to_i64(&self) -> Option<i64> {
+        if self.magnitude.len() > 2 {
+            return None;
+        }
+        let magnitude = self.magnitude.iter().rev().fold(0u64, |total, digit| (total << 32) | *digit as u64);
+        return if self.negative {
+            0i64.checked_sub_unsigned(magnitude)
+        } else {
+            i64::try_from(magnitude).ok()
+        };
+    }
+
+    fn plus(&self, other: &BigInt) -> Option<BigInt> {
+        if self.negative == other.negative {
+            return Some(BigInt::new(self.negative, add_magnitudes(&self.magnitude, &other.magnitude)));
+        }
+        return Some(match compare_magnitudes(&self.magnitude, &other.magnitude) {
+            Ordering::Less => BigInt::new(other.negative, subtract_magnitudes(&other.magnitude, &self.magnitude)),
+            _ => BigInt::new(self.negative, subtract_magnitudes(&self.magnitude, &other.magnitude)),
+        });
+    }
+
+    fn times(&self, other: &BigInt) -> Option<BigInt> {
+        return Some(BigInt::new(self.negative != other.negative, multiply_magnitudes(&self.magnitude, &other.magnitude)));
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &BigInt) -> Ordering {
+        return match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => compare_magnitudes(&self.magnitude, &other.magnitude),
+            (true, true) => compare_magnitudes(&other.magnitude, &self.magnitude),
+        };
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &BigInt) -> Option<Ordering> {
+        return Some(self.cmp(other));
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.magnitude.is_empty() {
+            return f.pad("0");
+        }
+
+        let mut magnitude = self.magnitude.clone();
+        let mut chunks = vec![];
+        while !magnitude.is_empty() {
+            chunks.push(divide_small(&mut magnitude, DECIMAL_BASE));
+        }
+        let mut text = String::from(if self.negative { "-" } else { "" });
+        text += &chunks.pop().unwrap().to_string();
+        for chunk in chunks.iter().rev() {
+            text += &format!("{:09}", chunk);
+        }
+        return f.pad(&text);
+    }
+}
+
+impl fmt::Debug for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}", self);
+    }
+}
+
+impl FromStr for BigInt {
+    type Err = ParseBigIntError;
+
+    fn from_str(text: &str) -> Result<BigInt, ParseBigIntError> {
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+            return Err(ParseBigIntError(text.to_owned()));
+        }
+
+        let mut magnitude = vec![];
+        // the first chunk takes the leftover digits so the rest are full
+        let first = match digits.len() % DECIMAL_DIGITS {
+            0 => DECIMAL_DIGITS,
+            leftover => leftover,
+        };
+        let mut start = 0;
+        let mut end = first;
+        while start < digits.len() {
+            let chunk: u32 = digits[start..end].parse().unwrap();
+            multiply_add_small(&mut magnitude, 10u32.pow((end - start) as u32), chunk);
+            start = end;
+            end += DECIMAL_DIGITS;
+        }
+        return Ok(BigInt::new(negative, magnitude));
+    }
+}
+
+impl fmt::Display for ParseBigIntError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "invalid integer '{}'", self.0);
+    }
+}
+
+impl std::error::Error for ParseBigIntError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(text: &str) -> BigInt {
+        return text.parse().unwrap();
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        for text in &["0", "7", "-1", "4294967296", "-9223372036854775808", "123456789012345678901234567890"] {
+            assert_eq!(big(text).to_string(), *text);
+        }
+        assert_eq!(big("-0"), BigInt::zero());
+        assert_eq!(big("+000012"), BigInt::from_i64(12));
+        assert!("12a".parse::<BigInt>().is_err());
+        assert!("-".parse::<BigInt>().is_err());
+    }
+
+    #[test]
+    fn test_i64_conversions() {
+        for value in &[0, 1, -1, i64::MAX, i64::MIN, 1 << 40] {
+            assert_eq!(BigInt::from_i64(*value).to_i64(), Some(*value));
+        }
+        assert_eq!(big("9223372036854775808").to_i64(), None);
+        assert_eq!(big("-9223372036854775809").to_i64(), None);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let large = big("123456789012345678901234567890");
+
+        assert_eq!(large.times(&large).unwrap(), big("15241578753238836750495351562536198787501905199875019052100"));
+        assert_eq!(large.plus(&big("-123456789012345678901234567891")).unwrap(), BigInt::from_i64(-1));
+        assert_eq!(big("-4294967296").plus(&BigInt::from_i64(1)).unwrap(), big("-4294967295"));
+        assert_eq!(large.times(&BigInt::from_i64(-2)).unwrap(), big("-246913578024691357802469135780"));
+        assert!(large.times(&BigInt::zero()).unwrap().is_zero());
+    }
+
+    #[test]
+    fn test_ordering() {
+        let mut values = [big("5"), big("-99999999999999999999"), big("0"), big("99999999999999999999"), big("-5")];
+        values.sort();
+
+        let sorted: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+        assert_eq!(sorted, vec!["-99999999999999999999", "-5", "0", "5", "99999999999999999999"]);
+    }
+}
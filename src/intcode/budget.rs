@@ -0,0 +1,61 @@
+use std::time::{Duration, Instant};
+
+/// Limits how long `run` may go before returning `RunState::BudgetExhausted`.
+/// Unlimited by default.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Budget {
+    pub steps: Option<u64>,
+    pub time: Option<Duration>,
+}
+
+impl Budget {
+    pub fn unlimited() -> Budget {
+        return Budget::default();
+    }
+
+    pub fn steps(steps: u64) -> Budget {
+        return Budget { steps: Some(steps), time: None };
+    }
+
+    pub fn time(time: Duration) -> Budget {
+        return Budget { steps: None, time: Some(time) };
+    }
+
+    /// Whichever of the two limits is reached first ends the run.
+    pub fn with_time(self, time: Duration) -> Budget {
+        return Budget { time: Some(time), ..self };
+    }
+}
+
+/// A budget pinned to the instruction count and clock at the moment it was set.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Limit {
+    step_limit: Option<u64>,
+    deadline: Option<Instant>,
+}
+
+/// How many instructions to execute between clock reads.
+const CLOCK_INTERVAL: u64 = 1024;
+
+impl Limit {
+    pub(crate) fn new(budget: Budget, executed: u64) -> Limit {
+        return Limit {
+            step_limit: budget.steps.map(|steps| executed.saturating_add(steps)),
+            deadline: budget.time.map(|time| Instant::now() + time),
+        };
+    }
+
+    pub(crate) fn reached(&self, executed: u64) -> bool {
+        if let Some(step_limit) = self.step_limit {
+            if executed >= step_limit {
+                return true;
+            }
+        }
+        if let Some(deadline) = self.deadline {
+            if executed.is_multiple_of(CLOCK_INTERVAL) && Instant::now() >= deadline {
+                return true;
+            }
+        }
+        return false;
+    }
+}
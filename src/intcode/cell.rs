@@ -0,0 +1,117 @@
+use std::fmt;
+use std::num::ParseIntError;
+use std::str::FromStr;
+
+/// A value held in one memory cell, and so every value a program computes.
+/// Opcodes, addresses, jump targets and relative base offsets must still fit
+/// in an `i64`.
+pub trait Cell: Clone + PartialEq + PartialOrd + FromStr + fmt::Debug + fmt::Display + Send + 'static {
+    fn from_i64(value: i64) -> Self;
+
+    /// `None` if the value does not fit.
+    fn to_i64(&self) -> Option<i64>;
+
+    /// `None` if the sum cannot be represented, which faults the machine.
+    fn plus(&self, other: &Self) -> Option<Self>;
+
+    /// `None` if the product cannot be represented, which faults the machine.
+    fn times(&self, other: &Self) -> Option<Self>;
+
+    fn zero() -> Self {
+        return Self::from_i64(0);
+    }
+
+    fn is_zero(&self) -> bool {
+        return *self == Self::zero();
+    }
+}
+
+/// Wraps on overflow in every build, as the machine always did in release builds.
+impl Cell for i64 {
+    fn from_i64(value: i64) -> i64 {
+        return value;
+    }
+
+    fn to_i64(&self) -> Option<i64> {
+        return Some(*self);
+    }
+
+    fn plus(&self, other: &i64) -> Option<i64> {
+        return Some(self.wrapping_add(*other));
+    }
+
+    fn times(&self, other: &i64) -> Option<i64> {
+        return Some(self.wrapping_mul(*other));
+    }
+}
+
+/// Wraps on overflow in every build.
+impl Cell for i128 {
+    fn from_i64(value: i64) -> i128 {
+        return value as i128;
+    }
+
+    fn to_i64(&self) -> Option<i64> {
+        return if *self >= i64::MIN as i128 && *self <= i64::MAX as i128 { Some(*self as i64) } else { None };
+    }
+
+    fn plus(&self, other: &i128) -> Option<i128> {
+        return Some(self.wrapping_add(*other));
+    }
+
+    fn times(&self, other: &i128) -> Option<i128> {
+        return Some(self.wrapping_mul(*other));
+    }
+}
+
+/// An `i64` that faults the machine with `ErrorReason::Overflow` instead of
+/// wrapping.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Default)]
+pub struct CheckedI64(pub i64);
+
+impl Cell for CheckedI64 {
+    fn from_i64(value: i64) -> CheckedI64 {
+        return CheckedI64(value);
+    }
+
+    fn to_i64(&self) -> Option<i64> {
+        return Some(self.0);
+    }
+
+    fn plus(&self, other: &CheckedI64) -> Option<CheckedI64> {
+        return self.0.checked_add(other.0).map(CheckedI64);
+    }
+
+    fn times(&self, other: &CheckedI64) -> Option<CheckedI64> {
+        return self.0.checked_mul(other.0).map(CheckedI64);
+    }
+}
+
+impl fmt::Display for CheckedI64 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}", self.0);
+    }
+}
+
+impl FromStr for CheckedI64 {
+    type Err = ParseIntError;
+
+    fn from_str(text: &str) -> Result<CheckedI64, ParseIntError> {
+        return text.parse().map(CheckedI64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overflow() {
+        assert_eq!(i64::MAX.plus(&1), Some(i64::MIN));
+        assert_eq!(CheckedI64(i64::MAX).plus(&CheckedI64(1)), None);
+        assert_eq!(CheckedI64(1 << 32).times(&CheckedI64(1 << 32)), None);
+        assert_eq!((1i128 << 32).times(&(1 << 32)), Some(1 << 64));
+        assert_eq!((1i128 << 64).to_i64(), None);
+        assert_eq!((-5i128).to_i64(), Some(-5));
+    }
+}
@@ -0,0 +1,243 @@
+use std::collections::BTreeSet;
+
+use super::disassembler::{decode, Line, Operand};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Target {
+    Address(usize),
+    /// A jump through memory or the relative base, which static analysis
+    /// cannot follow.
+    Unknown,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum EdgeKind {
+    /// Straight on to the next instruction, including a conditional jump not taken.
+    Fallthrough,
+    Jump,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Edge {
+    pub kind: EdgeKind,
+    pub target: Target,
+}
+
+/// A run of instructions entered only at the top and left only at the bottom.
+/// A block that ends in a word that does not decode has no edges.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Block {
+    pub start: usize,
+    pub lines: Vec<Line>,
+    pub edges: Vec<Edge>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Cfg {
+    /// In address order.
+    pub blocks: Vec<Block>,
+}
+
+/// Follows every statically known path from address 0, splitting blocks at
+/// JT, JF and HLT and at every jump target.
+pub fn control_flow_graph(intcode: &[i64]) -> Cfg {
+    return control_flow_graph_from(intcode, &[0]);
+}
+
+/// Like `control_flow_graph`, also starting a block at each of `roots`.
+pub fn control_flow_graph_from(intcode: &[i64], roots: &[usize]) -> Cfg {
+    let mut leaders: BTreeSet<usize> = roots.iter().cloned().collect();
+    let mut reached = BTreeSet::new();
+    let mut work = roots.to_vec();
+
+    while let Some(address) = work.pop() {
+        if address >= intcode.len() || !reached.insert(address) {
+            continue;
+        }
+        let (_, edges, ends_block) = step(intcode, address);
+        for edge in edges {
+            if let Target::Address(target) = edge.target {
+                if ends_block {
+                    leaders.insert(target);
+                }
+                work.push(target);
+            }
+        }
+    }
+
+    let blocks = leaders.iter()
+        .filter(|leader| reached.contains(leader))
+        .map(|leader| block(intcode, *leader, &leaders))
+        .collect();
+    return Cfg { blocks };
+}
+
+fn block(intcode: &[i64], start: usize, leaders: &BTreeSet<usize>) -> Block {
+    let mut lines = vec![];
+    let mut address = start;
+    loop {
+        let (line, edges, ends_block) = step(intcode, address);
+        address += line.size();
+        lines.push(line);
+
+        if ends_block || leaders.contains(&address) || address >= intcode.len() {
+            return Block { start, lines, edges };
+        }
+    }
+}
+
+/// The instruction at `address`, where it can go next, and whether it ends a block.
+fn step(intcode: &[i64], address: usize) -> (Line, Vec<Edge>, bool) {
+    let line = match decode(intcode, address) {
+        Some(line) => line,
+        None => return (Line::Data { address, value: intcode[address] }, vec![], true),
+    };
+    let next = Edge { kind: EdgeKind::Fallthrough, target: Target::Address(address + line.size()) };
+
+    let (mnemonic, operands) = match &line {
+        Line::Instruction { mnemonic, operands, .. } => (*mnemonic, operands.clone()),
+        Line::Data { .. } => unreachable!("decode only returns instructions"),
+    };
+    let edges = match mnemonic {
+        "HLT" => vec![],
+        "JT" | "JF" => {
+            let target = match operands[1] {
+                Operand::Immediate(target) if target >= 0 => Target::Address(target as usize),
+                _ => Target::Unknown,
+            };
+            let jump = Edge { kind: EdgeKind::Jump, target };
+            // an immediate condition makes the jump unconditional, or dead
+            let jumps_if = mnemonic == "JT";
+            match operands[0] {
+                Operand::Immediate(condition) if (condition != 0) == jumps_if => vec![jump],
+                Operand::Immediate(_) => vec![next],
+                _ => vec![jump, next],
+            }
+        },
+        _ => return (line, vec![next], false),
+    };
+
+    return (line, edges, true);
+}
+
+impl Cfg {
+    /// Graphviz source with one node per block. Unknown targets share a
+    /// single `?` node, and targets outside the program get a dashed node.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph intcode {\n    node [shape=box, fontname=\"monospace\"];\n");
+        let starts: BTreeSet<usize> = self.blocks.iter().map(|block| block.start).collect();
+        let mut outside = BTreeSet::new();
+        let mut unknown = false;
+
+        for block in &self.blocks {
+            let label: Vec<String> = block.lines.iter().map(|line| format!("{}\\l", line.to_string().trim_start())).collect();
+            dot += &format!("    b{} [label=\"{}\"];\n", block.start, label.join(""));
+        }
+        for block in &self.blocks {
+            for edge in &block.edges {
+                let style = match edge.kind {
+                    EdgeKind::Jump => " [label=\"jump\"]",
+                    EdgeKind::Fallthrough => "",
+                };
+                match edge.target {
+                    Target::Address(target) => {
+                        if !starts.contains(&target) {
+                            outside.insert(target);
+                        }
+                        dot += &format!("    b{} -> b{}{};\n", block.start, target, style);
+                    },
+                    Target::Unknown => {
+                        unknown = true;
+                        dot += &format!("    b{} -> unknown [style=dashed];\n", block.start);
+                    },
+                }
+            }
+        }
+
+        for address in outside {
+            dot += &format!("    b{} [label=\"{}\", style=dashed];\n", address, address);
+        }
+        if unknown {
+            dot += "    unknown [label=\"?\", shape=diamond];\n";
+        }
+        dot += "}\n";
+        return dot;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(cfg: &Cfg) -> Vec<(usize, usize, Vec<Target>)> {
+        return cfg.blocks.iter()
+            .map(|block| (block.start, block.lines.len(), block.edges.iter().map(|edge| edge.target).collect()))
+            .collect();
+    }
+
+    #[test]
+    fn test_blocks() {
+        // counts address 20 down from 3, outputting each value, then jumps through address 21
+        let intcode = vec![
+            4,20,               // 0
+            1001,20,-1,20,      // 2
+            1005,20,0,          // 6
+            1106,0,13,          // 9
+            12345,              // 12, never reached
+            1105,1,17,          // 13
+            99,                 // 16, never reached
+            5,22,21,            // 17
+            99,                 // 20
+            3,                  // 21
+            0,                  // 22
+        ];
+        let expected = vec![
+            (0, 3, vec![Target::Address(0), Target::Address(9)]),
+            (9, 1, vec![Target::Address(13)]),
+            (13, 1, vec![Target::Address(17)]),
+            (17, 1, vec![Target::Unknown, Target::Address(20)]),
+            (20, 1, vec![]),
+        ];
+
+        assert_eq!(summary(&control_flow_graph(&intcode)), expected);
+    }
+
+    #[test]
+    fn test_relative_jump_is_unknown() {
+        let intcode = vec![109,5,2005,6,0,99,1];
+
+        let cfg = control_flow_graph(&intcode);
+
+        assert_eq!(summary(&cfg), vec![(0, 2, vec![Target::Unknown, Target::Address(5)]), (5, 1, vec![])]);
+    }
+
+    #[test]
+    fn test_dot() {
+        let intcode = vec![1105,1,4,42,1006,7,10,0];
+        let expected = "\
+digraph intcode {
+    node [shape=box, fontname=\"monospace\"];
+    b0 [label=\"0: JT    #1, #4\\l\"];
+    b4 [label=\"4: JF    7, #10\\l\"];
+    b7 [label=\"7: .data 0\\l\"];
+    b0 -> b4 [label=\"jump\"];
+    b4 -> b10 [label=\"jump\"];
+    b4 -> b7;
+    b10 [label=\"10\", style=dashed];
+}
+";
+
+        assert_eq!(control_flow_graph(&intcode).to_dot(), expected);
+    }
+
+    #[test]
+    fn test_puzzle_inputs() {
+        let cfg = control_flow_graph(&super::super::parse(include_str!("../../resources/9.input")));
+        assert_eq!(cfg.blocks.len(), 34);
+
+        // day 5 writes the opcode at address 6 before running it, which static analysis cannot see
+        let cfg = control_flow_graph(&super::super::parse(include_str!("../../resources/5.input")));
+        assert_eq!(cfg.blocks.len(), 1);
+        assert_eq!(cfg.blocks[0].lines.last(), Some(&Line::Data { address: 6, value: 1100 }));
+    }
+}
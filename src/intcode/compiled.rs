@@ -0,0 +1,309 @@
+use std::collections::VecDeque;
+use std::ops::{Deref, DerefMut};
+
+use super::computer::{IntCodeComputer, RunState};
+use super::disassembler::{disassemble, operation_from_mnemonic, Line, Operand};
+use super::error::{ErrorReason, IntcodeError};
+use super::io::{InputSource, OutputSink};
+use super::snapshot::Snapshot;
+
+type Op<I, O> = Box<dyn Fn(&mut IntCodeComputer<I, O>) -> Result<RunState, IntcodeError> + Send>;
+
+/// Runs a program as one closure per instruction, translated when the program
+/// is loaded with its operands and modes already bound. An instruction whose
+/// words are overwritten is dropped and run by the interpreter from then on,
+/// as is anything the translation could not cover.
+///
+/// Everything other than running goes through the wrapped machine. Change
+/// memory with `poke` rather than through `intcode` directly, so that
+/// overwritten code is noticed.
+pub struct CompiledComputer<I = VecDeque<i64>, O = Vec<i64>> {
+    machine: IntCodeComputer<I, O>,
+    ops: Vec<Option<Op<I, O>>>,
+    /// The address of the translated instruction covering each address.
+    owners: Vec<Option<usize>>,
+}
+
+impl CompiledComputer {
+    pub fn new(intcode: &[i64], default_inputs: Vec<i64>) -> CompiledComputer {
+        return CompiledComputer::from_machine(IntCodeComputer::new(intcode, default_inputs));
+    }
+}
+
+impl<I: InputSource, O: OutputSink> CompiledComputer<I, O> {
+    pub fn from_machine(mut machine: IntCodeComputer<I, O>) -> CompiledComputer<I, O> {
+        machine.watch_writes();
+        let mut computer = CompiledComputer { machine, ops: vec![], owners: vec![] };
+        computer.translate();
+        return computer;
+    }
+
+    /// The machine, no longer watching for writes to retranslate.
+    pub fn into_machine(mut self) -> IntCodeComputer<I, O> {
+        self.machine.unwatch_writes();
+        return self.machine;
+    }
+
+    /// Number of instructions still running translated.
+    pub fn translated(&self) -> usize {
+        return self.ops.iter().filter(|op| op.is_some()).count();
+    }
+
+    /// Like `IntCodeComputer::restore`, and translates the restored memory.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.machine.restore(snapshot);
+        self.translate();
+    }
+
+    pub fn run(&mut self) -> Result<RunState, IntcodeError> {
+        loop {
+            if self.machine.out_of_budget() {
+                return Ok(RunState::BudgetExhausted);
+            }
+            match self.next_operation()? {
+                RunState::Running | RunState::OutputReady => continue,
+                state => return Ok(state),
+            }
+        }
+    }
+
+    pub fn run_until_output(&mut self) -> Result<RunState, IntcodeError> {
+        loop {
+            if self.machine.out_of_budget() {
+                return Ok(RunState::BudgetExhausted);
+            }
+            match self.next_operation()? {
+                RunState::Running => continue,
+                state => return Ok(state),
+            }
+        }
+    }
+
+    pub fn next_operation(&mut self) -> Result<RunState, IntcodeError> {
+        while let Some(address) = self.machine.pop_written() {
+            if let Some(Some(owner)) = self.owners.get(address) {
+                self.ops[*owner] = None;
+            }
+        }
+
+        let op = match self.ops.get(self.machine.ptr) {
+            Some(Some(op)) if !self.machine.needs_interpreter() && !self.machine.halted => op,
+            _ => return self.machine.next_operation(),
+        };
+        let state = op(&mut self.machine)?;
+        self.machine.halted = state == RunState::Halted;
+        if state != RunState::AwaitingInput {
+            self.machine.executed += 1;
+        }
+        return Ok(state);
+    }
+
+    fn translate(&mut self) {
+        while self.machine.pop_written().is_some() {}
+        let intcode = self.machine.intcode.segments()[0].1.to_vec();
+        self.ops = (0..intcode.len()).map(|_| None).collect();
+        self.owners = vec![None; intcode.len()];
+
+        for line in disassemble(&intcode) {
+            if let Line::Instruction { address, mnemonic, operands } = line {
+                let operation = operation_from_mnemonic(mnemonic).unwrap();
+                if let Some(op) = compile(operation, &operands) {
+                    self.ops[address] = Some(op);
+                    for owner in &mut self.owners[address..=address + operands.len()] {
+                        *owner = Some(address);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<I, O> Deref for CompiledComputer<I, O> {
+    type Target = IntCodeComputer<I, O>;
+
+    fn deref(&self) -> &IntCodeComputer<I, O> {
+        return &self.machine;
+    }
+}
+
+impl<I, O> DerefMut for CompiledComputer<I, O> {
+    fn deref_mut(&mut self) -> &mut IntCodeComputer<I, O> {
+        return &mut self.machine;
+    }
+}
+
+/// A parameter with its mode resolved at translation time.
+#[derive(Debug, Clone, Copy)]
+enum Arg {
+    Immediate(i64),
+    Position(usize),
+    Relative(i64),
+}
+
+/// Returns `None` for operands the interpreter would fault on, so that the
+/// interpreter reports the fault.
+fn arg(operand: Operand) -> Option<Arg> {
+    return match operand {
+        Operand::Immediate(value) => Some(Arg::Immediate(value)),
+        Operand::Position(address) if address >= 0 => Some(Arg::Position(address as usize)),
+        Operand::Position(_) => None,
+        Operand::Relative(offset) => Some(Arg::Relative(offset)),
+    };
+}
+
+/// Writes never use immediate mode; the interpreter treats it as position mode.
+fn target(operand: Operand) -> Option<Arg> {
+    return match operand {
+        Operand::Immediate(address) => arg(Operand::Position(address)),
+        operand => arg(operand),
+    };
+}
+
+fn address<I: InputSource, O: OutputSink>(machine: &IntCodeComputer<I, O>, arg: Arg) -> Result<usize, IntcodeError> {
+    return match arg {
+        Arg::Position(address) => Ok(address),
+        Arg::Relative(offset) => {
+            let address = machine.relative_base.checked_add(offset)
+                .ok_or_else(|| machine.fault(ErrorReason::Overflow))?;
+            if address < 0 {
+                return Err(machine.fault(ErrorReason::NegativeAddress(address)));
+            }
+            Ok(address as usize)
+        },
+        Arg::Immediate(_) => unreachable!("immediate operands are never addressed"),
+    };
+}
+
+fn read<I: InputSource, O: OutputSink>(machine: &IntCodeComputer<I, O>, arg: Arg) -> Result<i64, IntcodeError> {
+    if let Arg::Immediate(value) = arg {
+        return Ok(value);
+    }
+    return Ok(machine.get_value(address(machine, arg)?));
+}
+
+fn write<I: InputSource, O: OutputSink>(machine: &mut IntCodeComputer<I, O>, arg: Arg, value: i64) -> Result<(), IntcodeError> {
+    let address = address(machine, arg)?;
+    return machine.replace_value(address, value);
+}
+
+fn compile<I: InputSource, O: OutputSink>(operation: i64, operands: &[Operand]) -> Option<Op<I, O>> {
+    let op: Op<I, O> = match operation {
+        1 | 2 | 7 | 8 => {
+            let (left, right, result) = (arg(operands[0])?, arg(operands[1])?, target(operands[2])?);
+            let apply: fn(i64, i64) -> i64 = match operation {
+                1 => |left, right| left.wrapping_add(right),
+                2 => |left, right| left.wrapping_mul(right),
+                7 => |left, right| (left < right) as i64,
+                _ => |left, right| (left == right) as i64,
+            };
+            Box::new(move |machine| {
+                let value = apply(read(machine, left)?, read(machine, right)?);
+                write(machine, result, value)?;
+                machine.ptr += 4;
+                return Ok(RunState::Running);
+            })
+        },
+        3 => {
+            let result = target(operands[0])?;
+            Box::new(move |machine| {
+                let value = match machine.inputs.read() {
+                    Some(value) => value,
+                    None => return Ok(RunState::AwaitingInput),
+                };
+                write(machine, result, value)?;
+                machine.ptr += 2;
+                return Ok(RunState::Running);
+            })
+        },
+        4 => {
+            let value = arg(operands[0])?;
+            Box::new(move |machine| {
+                let value = read(machine, value)?;
+                machine.outputs.write(value);
+                machine.ptr += 2;
+                return Ok(RunState::OutputReady);
+            })
+        },
+        5 | 6 => {
+            let (condition, destination) = (arg(operands[0])?, arg(operands[1])?);
+            let jump_if = operation == 5;
+            Box::new(move |machine| {
+                let condition = read(machine, condition)?;
+                let destination = read(machine, destination)?;
+                if (condition != 0) == jump_if {
+                    machine.ptr = machine.jump_target(destination)?;
+                } else {
+                    machine.ptr += 3;
+                }
+                return Ok(RunState::Running);
+            })
+        },
+        9 => {
+            let offset = arg(operands[0])?;
+            Box::new(move |machine| {
+                let offset = read(machine, offset)?;
+                machine.relative_base = machine.relative_base.checked_add(offset)
+                    .ok_or_else(|| machine.fault(ErrorReason::Overflow))?;
+                machine.ptr += 2;
+                return Ok(RunState::Running);
+            })
+        },
+        99 => Box::new(|_| Ok(RunState::Halted)),
+        _ => return None,
+    };
+
+    return Some(op);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translates_program() {
+        let intcode = include_str!("../../resources/9.input");
+        let mut computer = CompiledComputer::new(&super::super::parse(intcode), vec![2]);
+        let translated = computer.translated();
+
+        computer.run().unwrap();
+
+        assert_eq!(computer.outputs, vec![50008]);
+        assert!(translated > 100);
+        assert_eq!(computer.translated(), translated);
+    }
+
+    #[test]
+    fn test_overwritten_code_falls_back() {
+        // adds 5 and 6, then rewrites the ADD at address 0 to a MUL and runs it again
+        let intcode: Vec<i64> = vec![1101,5,6,30,4,30,1008,0,1102,31,1005,31,20,1101,1102,0,0,1105,1,0,99];
+        let mut computer = CompiledComputer::new(&intcode, vec![]);
+        let translated = computer.translated();
+
+        computer.run().unwrap();
+
+        assert_eq!(computer.outputs, vec![11, 30]);
+        assert_eq!(computer.translated(), translated - 1);
+    }
+
+    #[test]
+    fn test_poke_drops_translation() {
+        let intcode: Vec<i64> = vec![1101,1,1,5,104,0,99];
+        let mut computer = CompiledComputer::new(&intcode, vec![]);
+
+        computer.poke(2, 5).unwrap();
+        computer.run().unwrap();
+
+        assert_eq!(computer.outputs, vec![6]);
+    }
+
+    #[test]
+    fn test_into_machine_stops_watching_writes() {
+        let computer = CompiledComputer::new(&[1101,1,1,5,99,0], vec![]);
+
+        let mut machine = computer.into_machine();
+        machine.run().unwrap();
+
+        assert_eq!(machine.peek(5), 2);
+        assert!(machine.written().is_empty());
+    }
+}
@@ -0,0 +1,1349 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::ops::Range;
+
+use super::budget::{Budget, Limit};
+use super::cell::Cell;
+use super::decode::{DecodeCache, Instruction};
+use super::devices::Device;
+use super::error::{IntcodeError, ErrorReason};
+use super::history::{History, UndoRecord};
+use super::host::{HostCall, HostContext, SYSCALL};
+use super::io::{InputSource, OutputSink};
+use super::memory::{Memory, PagedMemory};
+use super::opcodes::{Behaviour, Effect, OpcodeTable};
+use super::protection::Protection;
+use super::snapshot::Snapshot;
+use super::trace::{MemoryWrite, TraceEvent, TraceSink};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RunState {
+    Running,
+    OutputReady,
+    AwaitingInput,
+    Halted,
+    /// The budget given to `set_budget` ran out. Running again resumes once
+    /// the budget is raised or cleared.
+    BudgetExhausted,
+}
+
+pub struct IntCodeComputer<I = VecDeque<i64>, O = Vec<i64>, M: Memory = PagedMemory> {
+    pub intcode: M,
+    pub inputs: I,
+    pub outputs: O,
+    pub(crate) ptr: usize,
+    pub(crate) relative_base: i64,
+    pub(crate) executed: u64,
+    /// Set once HLT runs, so running again neither repeats nor counts it.
+    pub(crate) halted: bool,
+    limit: Option<Limit>,
+    decoded: DecodeCache,
+    /// Addresses written since last drained, when something is watching for
+    /// self-modifying code.
+    written: Option<Vec<usize>>,
+    tracer: Option<Box<dyn TraceSink<M::Cell> + Send>>,
+    event: Option<TraceEvent<M::Cell>>,
+    history: Option<History<M::Cell>>,
+    undo: Option<UndoRecord<M::Cell>>,
+    protection: Option<Protection>,
+    opcodes: OpcodeTable<M::Cell>,
+    host_calls: BTreeMap<i64, HostCall<M::Cell>>,
+    devices: Vec<Mapping<M::Cell>>,
+}
+
+/// A device and the addresses it is mapped over.
+type Mapping<C> = (Range<usize>, Box<dyn Device<C> + Send>);
+
+impl IntCodeComputer {
+    pub fn new(intcode: &[i64], default_inputs: Vec<i64>) -> IntCodeComputer {
+        return IntCodeComputer::with_io(intcode, default_inputs.into_iter().collect(), vec![]);
+    }
+}
+
+/// Clones the machine and its queues. Tracers, host calls and devices are not cloned.
+impl<I: Clone, O: Clone, M: Memory + Clone> Clone for IntCodeComputer<I, O, M> {
+    fn clone(&self) -> IntCodeComputer<I, O, M> {
+        return IntCodeComputer {
+            intcode: self.intcode.clone(),
+            inputs: self.inputs.clone(),
+            outputs: self.outputs.clone(),
+            ptr: self.ptr,
+            relative_base: self.relative_base,
+            executed: self.executed,
+            halted: self.halted,
+            limit: self.limit,
+            decoded: self.decoded.clone(),
+            written: self.written.clone(),
+            tracer: None,
+            event: None,
+            history: self.history.clone(),
+            undo: None,
+            protection: self.protection.clone(),
+            opcodes: self.opcodes.clone(),
+            host_calls: BTreeMap::new(),
+            devices: vec![],
+        };
+    }
+}
+
+impl<I: InputSource, O: OutputSink> IntCodeComputer<I, O> {
+    pub fn with_io(intcode: &[i64], inputs: I, outputs: O) -> IntCodeComputer<I, O> {
+        return IntCodeComputer::with_memory(PagedMemory::new(intcode), inputs, outputs);
+    }
+}
+
+impl<C: Cell> IntCodeComputer<VecDeque<C>, Vec<C>, PagedMemory<C>> {
+    /// Like `new`, for a machine whose cells are `C` rather than `i64`.
+    pub fn with_cells(intcode: &[C], default_inputs: Vec<C>) -> IntCodeComputer<VecDeque<C>, Vec<C>, PagedMemory<C>> {
+        return IntCodeComputer::with_memory(PagedMemory::with_cells(intcode), default_inputs.into_iter().collect(), vec![]);
+    }
+}
+
+impl<I: InputSource<M::Cell>, O: OutputSink<M::Cell>, M: Memory> IntCodeComputer<I, O, M> {
+    pub fn with_memory(intcode: M, inputs: I, outputs: O) -> IntCodeComputer<I, O, M> {
+        return IntCodeComputer {
+            intcode,
+            inputs,
+            outputs,
+            ptr: 0,
+            relative_base: 0,
+            executed: 0,
+            halted: false,
+            limit: None,
+            decoded: DecodeCache::new(),
+            written: None,
+            tracer: None,
+            event: None,
+            history: None,
+            undo: None,
+            protection: None,
+            opcodes: OpcodeTable::default(),
+            host_calls: BTreeMap::new(),
+            devices: vec![],
+        };
+    }
+
+    pub fn ptr(&self) -> usize {
+        return self.ptr;
+    }
+
+    pub fn set_ptr(&mut self, ptr: usize) {
+        self.ptr = ptr;
+        self.halted = false;
+    }
+
+    pub fn relative_base(&self) -> i64 {
+        return self.relative_base;
+    }
+
+    /// Number of instructions completed since the machine was created.
+    pub fn executed(&self) -> u64 {
+        return self.executed;
+    }
+
+    /// Limits `run` and `run_until_output` to `budget`, counted from now.
+    pub fn set_budget(&mut self, budget: Budget) {
+        self.limit = if budget == Budget::unlimited() {
+            None
+        } else {
+            Some(Limit::new(budget, self.executed))
+        };
+    }
+
+    /// Reads memory, or what a mapped device holds without disturbing it.
+    /// Unset addresses read as 0.
+    pub fn peek(&self, address: usize) -> M::Cell {
+        return self.get_value(address);
+    }
+
+    pub fn poke(&mut self, address: usize, value: M::Cell) -> Result<(), IntcodeError> {
+        return self.replace_value(address, value);
+    }
+
+    pub fn snapshot(&self) -> Snapshot<M> where M: Clone {
+        return Snapshot {
+            intcode: self.intcode.clone(),
+            ptr: self.ptr,
+            relative_base: self.relative_base,
+        };
+    }
+
+    /// Returns memory and registers to a snapshot. Queued input and output are left alone.
+    pub fn restore(&mut self, snapshot: &Snapshot<M>) where M: Clone {
+        self.intcode.clone_from(&snapshot.intcode);
+        self.decoded.clear();
+        self.ptr = snapshot.ptr;
+        self.halted = false;
+        self.relative_base = snapshot.relative_base;
+        if let Some(history) = &mut self.history {
+            history.clear();
+        }
+    }
+
+    /// Decoded instructions are cached by address unless this is turned off.
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        self.decoded.set_enabled(enabled);
+    }
+
+    /// Sends a `TraceEvent` to `tracer` for every instruction executed from now on.
+    pub fn set_tracer(&mut self, tracer: Box<dyn TraceSink<M::Cell> + Send>) {
+        self.tracer = Some(tracer);
+    }
+
+    pub fn take_tracer(&mut self) -> Option<Box<dyn TraceSink<M::Cell> + Send>> {
+        return self.tracer.take();
+    }
+
+    /// Keeps an undo record for each of the last `limit` instructions executed,
+    /// so that `step_back` can rewind them. `None` stops recording and forgets
+    /// the records kept so far. Pokes and writes to mapped devices are not
+    /// recorded.
+    pub fn set_history(&mut self, limit: Option<usize>) {
+        self.history = limit.map(History::new);
+    }
+
+    /// Number of instructions `step_back` can currently undo.
+    pub fn history_len(&self) -> usize {
+        return self.history.as_ref().map_or(0, |history| history.len());
+    }
+
+    /// How many times `step_back` must be called to undo the most recent
+    /// recorded write to `address`.
+    pub fn steps_since_write(&self, address: usize) -> Option<usize> {
+        return self.history.as_ref()?.steps_since_write(address);
+    }
+
+    /// Undoes the most recently executed instruction's effect on memory and
+    /// registers. The record is returned so that the caller can put back any
+    /// input it consumed and take back any output it produced.
+    pub fn step_back(&mut self) -> Option<UndoRecord<M::Cell>> {
+        let record = self.history.as_mut()?.pop()?;
+        for (address, old) in record.writes.iter().rev() {
+            self.intcode.set(*address, old.clone()).expect("Restoring a value the address held before cannot fail");
+            self.decoded.invalidate(*address);
+            if let Some(written) = &mut self.written {
+                written.push(*address);
+            }
+        }
+        self.ptr = record.ptr;
+        self.relative_base = record.relative_base;
+        self.halted = false;
+        self.executed -= 1;
+        return Some(record);
+    }
+
+    /// Checks every instruction fetch and write against a code map from now
+    /// on. `None` turns checking off.
+    pub fn set_protection(&mut self, protection: Option<Protection>) {
+        self.protection = protection;
+    }
+
+    pub fn protection(&self) -> Option<&Protection> {
+        return self.protection.as_ref();
+    }
+
+    /// Replaces the instruction set, by default the full day 9 one.
+    pub fn set_opcodes(&mut self, opcodes: OpcodeTable<M::Cell>) {
+        self.opcodes = opcodes;
+    }
+
+    pub fn opcodes(&self) -> &OpcodeTable<M::Cell> {
+        return &self.opcodes;
+    }
+
+    /// Makes `handler` the host service for `service`, replacing any before
+    /// it, and enables `SYS` in the current instruction set.
+    pub fn register_host_call(&mut self, service: i64, handler: HostCall<M::Cell>) {
+        self.opcodes.enable_host_calls();
+        self.host_calls.insert(service, handler);
+    }
+
+    /// Hands reads and writes by the program anywhere in `range` to `device`
+    /// instead of memory, and ticks it after every instruction. What a device
+    /// does is not undone by `step_back`. Panics if the range is empty or
+    /// overlaps a device already mapped.
+    pub fn map_device(&mut self, range: Range<usize>, device: Box<dyn Device<M::Cell> + Send>) {
+        assert!(!range.is_empty(), "A device needs at least one address");
+        if let Some((mapped, _)) = self.devices.iter().find(|(mapped, _)| mapped.start < range.end && range.start < mapped.end) {
+            panic!("Device at {:?} overlaps the device at {:?}", range, mapped);
+        }
+        self.devices.push((range, device));
+    }
+
+    /// Runs until the program halts, needs an input that has not been provided,
+    /// or uses up its budget.
+    pub fn run(&mut self) -> Result<RunState, IntcodeError> {
+        loop {
+            if self.out_of_budget() {
+                return Ok(RunState::BudgetExhausted);
+            }
+            match self.next_operation()? {
+                RunState::Running | RunState::OutputReady => continue,
+                state => return Ok(state),
+            }
+        }
+    }
+
+    /// Like `run`, but also stops after each value is output.
+    pub fn run_until_output(&mut self) -> Result<RunState, IntcodeError> {
+        loop {
+            if self.out_of_budget() {
+                return Ok(RunState::BudgetExhausted);
+            }
+            match self.next_operation()? {
+                RunState::Running => continue,
+                state => return Ok(state),
+            }
+        }
+    }
+
+    pub fn next_operation(&mut self) -> Result<RunState, IntcodeError> {
+        if self.halted {
+            return Ok(RunState::Halted);
+        }
+        let opcode = match self.get_value(self.ptr).to_i64() {
+            Some(opcode) => opcode,
+            None => return Err(self.fault(ErrorReason::UnknownOpcode)),
+        };
+        let instruction = self.decoded.decode(self.ptr, opcode);
+
+        if self.tracer.is_some() {
+            self.event = self.start_event(instruction);
+        }
+        if self.history.is_some() {
+            self.undo = Some(UndoRecord::new(self.ptr, self.relative_base));
+        }
+        if self.protection.is_some() {
+            self.protect(instruction)?;
+        }
+        let result = self.execute(instruction);
+        let event = self.event.take();
+        let undo = self.undo.take();
+        if let Some(protection) = &mut self.protection {
+            protection.next = None;
+        }
+
+        let state = result?;
+        self.halted = state == RunState::Halted;
+        if state != RunState::AwaitingInput {
+            self.executed += 1;
+            for (_, device) in &mut self.devices {
+                device.tick();
+            }
+        }
+        if let (Some(history), Some(undo)) = (&mut self.history, undo) {
+            if state != RunState::AwaitingInput {
+                history.push(undo);
+            }
+        }
+        if let (Some(tracer), Some(event)) = (&mut self.tracer, event) {
+            if state != RunState::AwaitingInput {
+                tracer.record(&event);
+            }
+        }
+
+        return Ok(state);
+    }
+
+    fn execute(&mut self, instruction: Instruction) -> Result<RunState, IntcodeError> {
+        let opcode = match self.opcodes.get(instruction.operation) {
+            Some(opcode) => opcode,
+            None => return Err(self.fault(ErrorReason::UnknownOpcode)),
+        };
+        let max_mode = self.opcodes.max_mode();
+        if let Some(mode) = instruction.modes[..opcode.parameters].iter().find(|mode| **mode > max_mode) {
+            return Err(self.fault(ErrorReason::UnknownParameterMode(*mode)));
+        }
+
+        let modes = instruction.modes;
+        let state = match opcode.behaviour {
+            Behaviour::Builtin(1) => self.add(modes)?,
+            Behaviour::Builtin(2) => self.multiply(modes)?,
+            Behaviour::Builtin(3) => self.input(modes)?,
+            Behaviour::Builtin(4) => self.output(modes)?,
+            Behaviour::Builtin(5) => self.jump_if_true(modes)?,
+            Behaviour::Builtin(6) => self.jump_if_false(modes)?,
+            Behaviour::Builtin(7) => self.less_then(modes)?,
+            Behaviour::Builtin(8) => self.equals(modes)?,
+            Behaviour::Builtin(9) => self.change_relative_base(modes)?,
+            Behaviour::Builtin(99) => RunState::Halted,
+            Behaviour::Builtin(SYSCALL) => self.host_call(modes)?,
+            Behaviour::Builtin(_) => return Err(self.fault(ErrorReason::UnknownOpcode)),
+            Behaviour::Registered(_) => self.registered(instruction)?,
+        };
+
+        return Ok(state);
+    }
+
+    fn host_call(&mut self, parameter_modes: [i64; 3]) -> Result<RunState, IntcodeError> {
+        let service = self.value_from_parameter(parameter_modes[0], self.ptr + 1)?;
+        let service = self.cell_to_i64(&service)?;
+        let argument = self.value_from_parameter(parameter_modes[1], self.ptr + 2)?;
+        if !self.host_calls.contains_key(&service) {
+            return Err(self.fault(ErrorReason::UnknownHostCall(service)));
+        }
+
+        let (intcode, devices) = (&self.intcode, &self.devices);
+        let memory = |address| peek_mapped(intcode, devices, address);
+        let mut context = HostContext::new(&memory);
+        let handler = self.host_calls.get_mut(&service).unwrap();
+        let result = handler(&mut context, argument);
+        let writes = context.into_writes();
+        let result = result.map_err(|reason| self.fault(reason))?;
+        for (address, value) in writes {
+            self.replace_value(address, value)?;
+        }
+        self.update_value_from_parameter(parameter_modes[2], self.ptr + 3, result)?;
+        self.ptr += 4;
+        return Ok(RunState::Running);
+    }
+
+    /// Runs an instruction added with `OpcodeTable::register`.
+    fn registered(&mut self, instruction: Instruction) -> Result<RunState, IntcodeError> {
+        let opcode = self.opcodes.get(instruction.operation).unwrap().clone();
+        let handler = match &opcode.behaviour {
+            Behaviour::Registered(handler) => handler,
+            Behaviour::Builtin(_) => unreachable!("only registered instructions have handlers"),
+        };
+        let mut values = Vec::with_capacity(opcode.parameters);
+        for index in (0..opcode.parameters).filter(|index| opcode.write != Some(*index)) {
+            values.push(self.value_from_parameter(instruction.modes[index], self.ptr + index + 1)?);
+        }
+
+        let effect = handler(&values).map_err(|reason| self.fault(reason))?;
+        let next = self.ptr + opcode.parameters + 1;
+        match effect {
+            Effect::Continue => (),
+            Effect::Write(value) => {
+                let write = opcode.write.ok_or_else(|| self.fault(ErrorReason::NoWriteParameter))?;
+                self.update_value_from_parameter(instruction.modes[write], self.ptr + write + 1, value)?;
+            },
+            Effect::Output(value) => {
+                if let Some(undo) = &mut self.undo {
+                    undo.output = Some(value.clone());
+                }
+                self.outputs.write(value);
+                self.ptr = next;
+                return Ok(RunState::OutputReady);
+            },
+            Effect::Jump(target) => {
+                self.ptr = self.jump_target(target)?;
+                return Ok(RunState::Running);
+            },
+            Effect::Halt => return Ok(RunState::Halted),
+        }
+        self.ptr = next;
+        return Ok(RunState::Running);
+    }
+
+    /// Whether something is watching each instruction in a way only the
+    /// interpreter supports.
+    pub(crate) fn needs_interpreter(&self) -> bool {
+        return self.tracer.is_some() || self.history.is_some() || self.protection.is_some() || !self.opcodes.is_standard()
+            || !self.devices.is_empty();
+    }
+
+    pub(crate) fn watch_writes(&mut self) {
+        self.written = Some(vec![]);
+    }
+
+    pub(crate) fn unwatch_writes(&mut self) {
+        self.written = None;
+    }
+
+    pub(crate) fn pop_written(&mut self) -> Option<usize> {
+        return self.written.as_mut()?.pop();
+    }
+
+    /// Addresses written since last drained, without draining them.
+    pub(crate) fn written(&self) -> &[usize] {
+        return self.written.as_deref().unwrap_or(&[]);
+    }
+
+    /// A halted machine has nothing left to spend a budget on.
+    pub(crate) fn out_of_budget(&self) -> bool {
+        if self.halted {
+            return false;
+        }
+        return match &self.limit {
+            Some(limit) => limit.reached(self.executed),
+            None => false,
+        };
+    }
+
+    /// Checks the fetch of `instruction` and notes where the next one will be
+    /// for checking its writes.
+    fn protect(&mut self, instruction: Instruction) -> Result<(), IntcodeError> {
+        let ptr = self.ptr;
+        let size = |opcode: Option<i64>| opcode
+            .and_then(|opcode| self.opcodes.info(Instruction::decode(opcode).operation))
+            .map_or(1, |(_, parameters)| parameters + 1);
+        let next = ptr + size(Some(instruction.opcode));
+        let next = next..next + size(self.get_value(next).to_i64());
+
+        let protection = self.protection.as_mut().unwrap();
+        if let Some(violation) = protection.check_execute(ptr) {
+            if protection.record(violation) {
+                return Err(self.fault(ErrorReason::Protection(violation.kind)));
+            }
+        }
+        protection.next = Some(next);
+        return Ok(());
+    }
+
+    fn start_event(&mut self, instruction: Instruction) -> Option<TraceEvent<M::Cell>> {
+        let (mnemonic, parameters) = self.opcodes.info(instruction.operation)?;
+        let parameters = (1..=parameters).map(|offset| self.get_value(self.ptr + offset)).collect();
+        return Some(TraceEvent {
+            ptr: self.ptr,
+            opcode: instruction.opcode,
+            mnemonic,
+            parameters,
+            operands: vec![],
+            reads: vec![],
+            writes: vec![],
+            relative_base: None,
+        });
+    }
+
+    pub(crate) fn fault(&self, reason: ErrorReason) -> IntcodeError {
+        // an opcode too large for the error is reported as the nearest i64
+        let opcode = self.intcode.get(self.ptr);
+        let opcode = match opcode.to_i64() {
+            Some(opcode) => opcode,
+            None if opcode > M::Cell::zero() => i64::MAX,
+            None => i64::MIN,
+        };
+        return IntcodeError::new(self.ptr, opcode, reason);
+    }
+
+    /// Values used as addresses, jump targets and relative base offsets must fit in an `i64`.
+    fn cell_to_i64(&self, value: &M::Cell) -> Result<i64, IntcodeError> {
+        return value.to_i64().ok_or_else(|| self.fault(ErrorReason::OutOfRange));
+    }
+
+    fn checked<T>(&self, result: Option<T>) -> Result<T, IntcodeError> {
+        return result.ok_or_else(|| self.fault(ErrorReason::Overflow));
+    }
+
+    fn add(&mut self, parameter_modes: [i64; 3]) -> Result<RunState, IntcodeError> {
+        let value1 = self.value_from_parameter(parameter_modes[0], self.ptr + 1)?;
+        let value2 = self.value_from_parameter(parameter_modes[1], self.ptr + 2)?;
+        let result = self.checked(value1.plus(&value2))?;
+        
+        self.update_value_from_parameter(parameter_modes[2], self.ptr + 3, result)?;
+        self.ptr += 4;
+        return Ok(RunState::Running);
+    }
+    
+    fn multiply(&mut self, parameter_modes: [i64; 3]) -> Result<RunState, IntcodeError> {
+        let value1 = self.value_from_parameter(parameter_modes[0], self.ptr + 1)?;
+        let value2 = self.value_from_parameter(parameter_modes[1], self.ptr + 2)?;
+        let result = self.checked(value1.times(&value2))?;
+        
+        self.update_value_from_parameter(parameter_modes[2], self.ptr + 3, result)?;
+        self.ptr += 4;
+        return Ok(RunState::Running);
+    }
+    
+    fn input(&mut self, parameter_modes: [i64; 3]) -> Result<RunState, IntcodeError> {
+        let input = match self.inputs.read() {
+            Some(input) => input,
+            None => {
+                // halt until input
+                return Ok(RunState::AwaitingInput);
+            }
+        };
+        if let Some(undo) = &mut self.undo {
+            undo.input = Some(input.clone());
+        }
+        
+        self.update_value_from_parameter(parameter_modes[0], self.ptr + 1, input)?;
+        self.ptr += 2;
+        return Ok(RunState::Running);
+    }
+    
+    fn output(&mut self, parameter_modes: [i64; 3]) -> Result<RunState, IntcodeError> {
+        let output = self.value_from_parameter(parameter_modes[0], self.ptr + 1)?;
+        if let Some(undo) = &mut self.undo {
+            undo.output = Some(output.clone());
+        }
+        self.outputs.write(output);
+        self.ptr += 2;
+        return Ok(RunState::OutputReady);
+    }
+    
+    fn jump_if_true(&mut self, parameter_modes: [i64; 3]) -> Result<RunState, IntcodeError> {
+        let value1 = self.value_from_parameter(parameter_modes[0], self.ptr + 1)?;
+        let value2 = self.value_from_parameter(parameter_modes[1], self.ptr + 2)?;
+        if !value1.is_zero() {
+            self.ptr = self.jump_target(self.cell_to_i64(&value2)?)?;
+        } else {
+            self.ptr += 3;
+        }
+        return Ok(RunState::Running);
+    }
+    
+    fn jump_if_false(&mut self, parameter_modes: [i64; 3]) -> Result<RunState, IntcodeError> {
+        let value1 = self.value_from_parameter(parameter_modes[0], self.ptr + 1)?;
+        let value2 = self.value_from_parameter(parameter_modes[1], self.ptr + 2)?;
+        if value1.is_zero() {
+            self.ptr = self.jump_target(self.cell_to_i64(&value2)?)?;
+        } else {
+            self.ptr += 3;
+        }
+        return Ok(RunState::Running);
+    }
+    
+    fn less_then(&mut self, parameter_modes: [i64; 3]) -> Result<RunState, IntcodeError> {
+        let value1 = self.value_from_parameter(parameter_modes[0], self.ptr + 1)?;
+        let value2 = self.value_from_parameter(parameter_modes[1], self.ptr + 2)?;
+
+        if value1 < value2 {
+            self.update_value_from_parameter(parameter_modes[2], self.ptr + 3, M::Cell::from_i64(1))?;
+        } else {
+            self.update_value_from_parameter(parameter_modes[2], self.ptr + 3, M::Cell::zero())?;
+        }
+        self.ptr += 4;
+        return Ok(RunState::Running);
+    }
+    
+    fn equals(&mut self, parameter_modes: [i64; 3]) -> Result<RunState, IntcodeError> {
+        let value1 = self.value_from_parameter(parameter_modes[0], self.ptr + 1)?;
+        let value2 = self.value_from_parameter(parameter_modes[1], self.ptr + 2)?;
+
+        if value1 == value2 {
+            self.update_value_from_parameter(parameter_modes[2], self.ptr + 3, M::Cell::from_i64(1))?;
+        } else {
+            self.update_value_from_parameter(parameter_modes[2], self.ptr + 3, M::Cell::zero())?;
+        }
+        self.ptr += 4;
+        return Ok(RunState::Running);
+    }
+
+    fn change_relative_base(&mut self, parameter_modes: [i64; 3]) -> Result<RunState, IntcodeError> {
+        let value = self.value_from_parameter(parameter_modes[0], self.ptr + 1)?;
+        let value1 = self.cell_to_i64(&value)?;
+        let old = self.relative_base;
+        self.relative_base = self.checked(old.checked_add(value1))?;
+        if let Some(event) = &mut self.event {
+            event.relative_base = Some((old, self.relative_base));
+        }
+        self.ptr += 2;
+        return Ok(RunState::Running);
+    }
+
+    pub(crate) fn jump_target(&self, value: i64) -> Result<usize, IntcodeError> {
+        if value < 0 {
+            return Err(self.fault(ErrorReason::NegativeAddress(value)));
+        }
+        return Ok(value as usize);
+    }
+
+    fn address_from_parameter(&mut self, parameter: i64, ptr: usize) -> Result<usize, IntcodeError> {
+        let address_at_pointer = self.cell_to_i64(&self.get_value(ptr))?;
+        let address = match parameter {
+            0 => address_at_pointer,
+            2 => self.checked(address_at_pointer.checked_add(self.relative_base))?,
+            _ => return Err(self.fault(ErrorReason::UnknownParameterMode(parameter)))
+        };
+        if address < 0 {
+            return Err(self.fault(ErrorReason::NegativeAddress(address)));
+        }
+
+        return Ok(address as usize);
+    }
+    
+    fn value_from_parameter(&mut self, parameter: i64, ptr: usize) -> Result<M::Cell, IntcodeError> {
+        if parameter == 1 {
+            let value = self.get_value(ptr);
+            if let Some(event) = &mut self.event {
+                event.operands.push(value.clone());
+            }
+            return Ok(value);
+        }
+        let address = self.address_from_parameter(parameter, ptr)?;
+        let value = match self.device_at(address) {
+            Some((index, offset)) => self.devices[index].1.read(offset).map_err(|reason| self.fault(reason))?,
+            None => self.get_value(address),
+        };
+        if let Some(event) = &mut self.event {
+            event.operands.push(value.clone());
+            event.reads.push(address);
+        }
+        return Ok(value);
+    }
+
+    fn update_value_from_parameter(&mut self, parameter: i64, ptr: usize, value: M::Cell) -> Result<(), IntcodeError> {
+        // Writes never use immediate mode, so treat it as position mode
+        let mode = if parameter == 1 { 0 } else { parameter };
+        let address = self.address_from_parameter(mode, ptr)?;
+        return self.replace_value(address, value);
+    }
+
+    pub(crate) fn replace_value(&mut self, address: usize, value: M::Cell) -> Result<(), IntcodeError> {
+        if let Some(protection) = &mut self.protection {
+            if let Some(violation) = protection.check_write(self.ptr, address) {
+                if protection.record(violation) {
+                    return Err(self.fault(ErrorReason::Protection(violation.kind)));
+                }
+            }
+        }
+        if let Some((index, offset)) = self.device_at(address) {
+            return self.write_device(index, offset, address, value);
+        }
+        let old = self.intcode.get(address);
+        let new = self.event.as_ref().map(|_| value.clone());
+        if let Err(reason) = self.intcode.set(address, value) {
+            return Err(self.fault(reason));
+        }
+        self.decoded.invalidate(address);
+        if let Some(written) = &mut self.written {
+            written.push(address);
+        }
+        if let (Some(event), Some(new)) = (&mut self.event, new) {
+            event.writes.push(MemoryWrite { address, old: old.clone(), new });
+        }
+        if let Some(undo) = &mut self.undo {
+            undo.writes.push((address, old));
+        }
+        return Ok(());
+    }
+
+    pub(crate) fn get_value(&self, address: usize) -> M::Cell {
+        return peek_mapped(&self.intcode, &self.devices, address);
+    }
+
+    /// The index of the device mapped over `address`, and the offset into it.
+    fn device_at(&self, address: usize) -> Option<(usize, usize)> {
+        if self.devices.is_empty() {
+            return None;
+        }
+        return self.devices.iter()
+            .position(|(range, _)| range.contains(&address))
+            .map(|index| (index, address - self.devices[index].0.start));
+    }
+
+    fn write_device(&mut self, index: usize, offset: usize, address: usize, value: M::Cell) -> Result<(), IntcodeError> {
+        let old = self.devices[index].1.peek(offset);
+        let new = self.event.as_ref().map(|_| value.clone());
+        if let Err(reason) = self.devices[index].1.write(offset, value) {
+            return Err(self.fault(reason));
+        }
+        if let (Some(event), Some(new)) = (&mut self.event, new) {
+            event.writes.push(MemoryWrite { address, old, new });
+        }
+        return Ok(());
+    }
+}
+
+/// What `address` holds, through any device mapped over it.
+fn peek_mapped<M: Memory>(intcode: &M, devices: &[Mapping<M::Cell>], address: usize) -> M::Cell {
+    if let Some((range, device)) = devices.iter().find(|(range, _)| range.contains(&address)) {
+        return device.peek(address - range.start);
+    }
+    return intcode.get(address);
+}
+
+pub fn operation_from_opcode(opcode: i64) -> i64 {
+    return opcode % 100;
+}
+
+pub fn modes_from_opcode(opcode: i64, parameters: usize) -> Vec<i64> {
+    let params = opcode / 100;
+    let mut results = vec![];
+    let base: i64 = 10;
+    for i in 0..parameters {
+        let power = (i + 1) as u32;
+        results.push((params % base.pow(power)) / base.pow(power-1));
+    }
+
+    return results;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::bigint::BigInt;
+    use super::super::cell::CheckedI64;
+    use super::super::compiled::CompiledComputer;
+    use super::super::parse_cells;
+    use std::time::Duration;
+
+    // Day 5 IntCode tests
+    #[test]
+    fn test_operation_from_opcode() {
+        let opcode = 1002;
+        assert_eq!(operation_from_opcode(opcode), 2);
+    }
+
+    #[test]
+    fn test_modes_from_opcode() {
+        let opcode = 11002;
+        let parameters = 3;
+        assert_eq!(modes_from_opcode(opcode, parameters), vec![0, 1, 1]);
+
+        let opcode = 1002;
+        let parameters = 3;
+        assert_eq!(modes_from_opcode(opcode, parameters), vec![0, 1, 0]);
+    }
+
+    #[test]
+    fn test_values_from_parameters() {
+        let intcode: Vec<i64> = vec![1002,4,3,4,33];
+        let ptr = 1;
+        let parameters: Vec<i64> = vec![0, 1, 0];
+        let expected = [33, 3, 33];
+
+        let mut computer = IntCodeComputer::new(&intcode, vec![]);
+
+        for (index, param) in parameters.iter().enumerate() {
+            assert_eq!(
+                computer.value_from_parameter(*param, ptr + index).unwrap(),
+                expected[index],
+                "\nFailed at index {} on value {}\n\n", index, param);
+        }
+    }
+
+    #[test]
+    fn test_decode_cache_follows_pokes_and_restores() {
+        // outputs 5, or the 42 at address 5 once the OUT is poked into position mode
+        let mut computer = IntCodeComputer::new(&[104,5,99,0,0,42], vec![]);
+        let start = computer.snapshot();
+        computer.run().unwrap();
+
+        computer.restore(&start);
+        computer.poke(0, 4).unwrap();
+        computer.run().unwrap();
+        computer.restore(&start);
+        computer.run().unwrap();
+
+        assert_eq!(computer.outputs, vec![5, 42, 5]);
+    }
+
+    // squares its input twice
+    const FOURTH_POWER: &str = "3,13,2,13,13,13,2,13,13,13,4,13,99,0";
+
+    #[test]
+    fn test_checked_cells_fault_on_overflow() {
+        let intcode = parse_cells::<CheckedI64>(FOURTH_POWER);
+        let mut computer = IntCodeComputer::with_cells(&intcode, vec![CheckedI64(3)]);
+        computer.run().unwrap();
+        assert_eq!(computer.outputs, vec![CheckedI64(81)]);
+
+        let mut computer = IntCodeComputer::with_cells(&intcode, vec![CheckedI64(10_000_000_000)]);
+        let error = computer.run().unwrap_err();
+        assert_eq!((error.ptr, error.reason), (2, ErrorReason::Overflow));
+    }
+
+    #[test]
+    fn test_i128_cells() {
+        let intcode = parse_cells::<i128>(FOURTH_POWER);
+        let mut computer = IntCodeComputer::with_cells(&intcode, vec![10_000_000_000]);
+
+        computer.run().unwrap();
+
+        assert_eq!(computer.outputs, vec![10_000_000_000i128.wrapping_pow(4)]);
+    }
+
+    #[test]
+    fn test_big_cells() {
+        let intcode = parse_cells::<BigInt>(FOURTH_POWER);
+        let mut computer = IntCodeComputer::with_cells(&intcode, vec!["10000000000".parse().unwrap()]);
+
+        computer.run().unwrap();
+
+        assert_eq!(computer.outputs[0].to_string(), format!("1{}", "0".repeat(40)));
+    }
+
+    #[test]
+    fn test_big_cells_run_puzzle_input() {
+        let intcode = parse_cells::<BigInt>(include_str!("../../resources/9.input"));
+        let mut computer = IntCodeComputer::with_cells(&intcode, vec![BigInt::from_i64(1)]);
+
+        computer.run().unwrap();
+
+        assert_eq!(computer.outputs, vec![BigInt::from_i64(2204990589)]);
+    }
+
+    #[test]
+    fn test_address_out_of_range() {
+        let intcode = parse_cells::<BigInt>("4,100000000000000000000,99");
+        let mut computer = IntCodeComputer::with_cells(&intcode, vec![]);
+
+        assert_eq!(computer.run().unwrap_err().reason, ErrorReason::OutOfRange);
+    }
+
+    /// Runs every test below against each backend.
+    macro_rules! backend_tests {
+        ($($backend:ident: $computer:ty),*) => { $(
+        mod $backend {
+            use super::*;
+
+            type Computer = $computer;
+
+            #[test]
+            fn test_day9_example1() {
+                let intcode = vec![109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99];
+                let expected = vec![109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99];
+
+                let mut computer = Computer::new(&intcode, vec![]);
+                computer.run().unwrap();
+
+                assert_eq!(computer.outputs, expected);
+            }
+
+            #[test]
+            fn test_day9_example2() {
+                let intcode = vec![1102,34915192,34915192,7,4,7,99,0];
+                let expected = 16;
+
+                let mut computer = Computer::new(&intcode, vec![]);
+                computer.run().unwrap();
+
+                assert_eq!(format!("{}", computer.outputs[0]).len(), expected);
+            }
+
+            #[test]
+            fn test_day9_example3() {
+                let intcode = vec![104,1125899906842624,99];
+                let expected = 1125899906842624;
+
+                let mut computer = Computer::new(&intcode, vec![]);
+                computer.run().unwrap();
+
+                assert_eq!(computer.outputs[0], expected);
+            }
+
+            #[test]
+            fn test_day9_203() {
+                let intcode = vec![9, 3, 203, 6, 4, 12, 99];
+                let inputs = vec![1];
+                let expected = 1;
+
+                let mut computer = Computer::new(&intcode, inputs);
+                computer.run().unwrap();
+
+                assert_eq!(computer.outputs[0], expected);
+            }
+
+            // OLD IntCode Computer tests
+            #[test]
+            fn test_run_intcode_example1() {
+                let intcode: Vec<i64> = vec![1,0,0,0,99];
+                let end = vec![2,0,0,0,99];
+                let mut computer = Computer::new(&intcode, vec![]);
+                computer.run().unwrap();
+
+                assert_eq!(computer.intcode, end);
+            }
+
+            #[test]
+            fn test_run_intcode_example2() {
+                let intcode: Vec<i64> = vec![2,3,0,3,99];
+                let end = vec![2,3,0,6,99];
+                let mut computer = Computer::new(&intcode, vec![]);
+                computer.run().unwrap();
+
+                assert_eq!(computer.intcode, end);
+            }
+
+            #[test]
+            fn test_run_intcode_example3() {
+                let intcode: Vec<i64> = vec![2,4,4,5,99,0];
+                let end = vec![2,4,4,5,99,9801];
+                let mut computer = Computer::new(&intcode, vec![]);
+                computer.run().unwrap();
+
+                assert_eq!(computer.intcode, end);
+            }
+
+            #[test]
+            fn test_run_intcode_example4() {
+                let intcode: Vec<i64> = vec![1,1,1,4,99,5,6,0,99];
+                let end = vec![30,1,1,4,2,5,6,0,99];
+                let mut computer = Computer::new(&intcode, vec![]);
+                computer.run().unwrap();
+
+                assert_eq!(computer.intcode, end);
+            }
+
+            #[test]
+            fn test_add() {
+                let intcode: Vec<i64> = vec![1001,4,3,4,33];
+                let expected = vec![1001,4,3,4,36];
+
+                let mut computer = Computer::new(&intcode, vec![]);
+
+                computer.next_operation().unwrap();
+
+                assert_eq!(computer.intcode, expected);
+                assert_eq!(computer.ptr, 4);
+            }
+
+            #[test]
+            fn test_multiply() {
+                let intcode: Vec<i64> = vec![1002,4,3,4,33];
+                let expected = vec![1002,4,3,4,99];
+
+                let mut computer = Computer::new(&intcode, vec![]);
+
+                computer.next_operation().unwrap();
+
+                assert_eq!(computer.intcode, expected);
+                assert_eq!(computer.ptr, 4);
+            }
+
+            #[test]
+            fn test_input() {
+                let intcode: Vec<i64> = vec![3,5,0,0,0,0];
+                let expected = vec![3,5,0,0,0,44];
+
+                let mut computer = Computer::new(&intcode, vec![44]);
+
+                computer.next_operation().unwrap();
+
+                assert_eq!(computer.intcode, expected);
+                assert_eq!(computer.ptr, 2);
+            }
+
+            #[test]
+            fn test_output() {
+                let intcode: Vec<i64> = vec![4,5,0,0,0,33];
+                let expected = vec![4,5,0,0,0,33];
+
+                let mut computer = Computer::new(&intcode, vec![]);
+
+                computer.next_operation().unwrap();
+
+                assert_eq!(computer.intcode, expected);
+                assert_eq!(computer.outputs, vec![33]);
+                assert_eq!(computer.ptr, 2);
+            }
+
+            #[test]
+            fn test_jump_if_true_mode_position_is_true() {
+                let intcode: Vec<i64> = vec![5,3,6,2,0,33,12];
+                let expected = 12;
+
+                let mut computer = Computer::new(&intcode, vec![]);
+
+                computer.next_operation().unwrap();
+
+                assert_eq!(computer.ptr, expected);
+            }
+
+            #[test]
+            fn test_jump_if_true_mode_immidiate_is_true() {
+                let intcode: Vec<i64> = vec![1105,1,6,0,0,33,12];
+                let expected = 6;
+
+                let mut computer = Computer::new(&intcode, vec![]);
+
+                computer.next_operation().unwrap();
+
+                assert_eq!(computer.ptr, expected);
+            }
+
+            #[test]
+            fn test_jump_if_false_mode_position_is_false() {
+                let intcode: Vec<i64> = vec![6,3,6,0,0,33,12];
+                let expected = 12;
+
+                let mut computer = Computer::new(&intcode, vec![]);
+
+                computer.next_operation().unwrap();
+
+                assert_eq!(computer.ptr, expected);
+            }
+
+            #[test]
+            fn test_jump_if_false_mode_immidiate_is_false() {
+                let intcode: Vec<i64> = vec![1106,0,6,0,0,33];
+                let expected = 6;
+
+                let mut computer = Computer::new(&intcode, vec![]);
+
+                computer.next_operation().unwrap();
+
+                assert_eq!(computer.ptr, expected);
+            }
+
+            #[test]
+            fn test_less_than_mode_position() {
+                let intcode: Vec<i64> = vec![7,4,5,6,6,7,7,24];
+                let expected = vec![7,4,5,6,6,7,1,24];
+                let expected_ptr = 4;
+
+                let mut computer = Computer::new(&intcode, vec![]);
+
+                computer.next_operation().unwrap();
+
+                assert_eq!(computer.intcode, expected);
+                assert_eq!(computer.ptr, expected_ptr);
+            }
+
+            #[test]
+            fn test_less_than_mode_immidiate() {
+                let intcode: Vec<i64> = vec![11107,3,6,5,5,33];
+                let expected = vec![11107,3,6,5,5,1];
+                let expected_ptr = 4;
+
+                let mut computer = Computer::new(&intcode, vec![]);
+
+                computer.next_operation().unwrap();
+
+                assert_eq!(computer.intcode, expected);
+                assert_eq!(computer.ptr, expected_ptr);
+            }
+
+            #[test]
+            fn test_input_example1() {
+                let intcode: Vec<i64> = vec![3,9,8,9,10,9,4,9,99,-1,8];
+                let expected = vec![3,9,8,9,10,9,4,9,99,8,8];
+
+                let mut computer = Computer::new(&intcode, vec![8]);
+
+                computer.next_operation().unwrap();
+
+                assert_eq!(computer.intcode, expected);
+                assert_eq!(computer.ptr, 2);
+            }
+
+            #[test]
+            fn test_jump_if_true_example6() {
+                let intcode: Vec<i64> = vec![3,3,1105,-1,9,1101,0,0,12,4,12,99,1];
+                let expected = 9;
+
+                let mut computer = Computer::new(&intcode, vec![]);
+                computer.ptr = 2;
+
+                computer.next_operation().unwrap();
+
+                assert_eq!(computer.ptr, expected);
+            }
+
+            #[test]
+            fn test_equals_example1() {
+                let intcode: Vec<i64> = vec![3,9,8,9,10,9,4,9,99,8,8];
+                let expected = vec![3,9,8,9,10,9,4,9,99,1,8];
+                let expected_ptr = 6;
+
+                let mut computer = Computer::new(&intcode, vec![]);
+                computer.ptr = 2;
+
+                computer.next_operation().unwrap();
+
+                assert_eq!(computer.intcode, expected);
+                assert_eq!(computer.ptr, expected_ptr);
+            }
+
+            #[test]
+            fn test_run_states() {
+                let intcode: Vec<i64> = vec![3,9,4,9,4,9,99,0,0,0];
+
+                let mut computer = Computer::new(&intcode, vec![]);
+
+                assert_eq!(computer.run().unwrap(), RunState::AwaitingInput);
+                computer.inputs.push_back(7);
+                assert_eq!(computer.run_until_output().unwrap(), RunState::OutputReady);
+                assert_eq!(computer.outputs, vec![7]);
+                assert_eq!(computer.run().unwrap(), RunState::Halted);
+                assert_eq!(computer.outputs, vec![7, 7]);
+            }
+
+            #[test]
+            fn test_unknown_opcode() {
+                let intcode: Vec<i64> = vec![1101,1,1,0,42,99];
+                let expected = IntcodeError::new(4, 42, ErrorReason::UnknownOpcode);
+
+                let mut computer = Computer::new(&intcode, vec![]);
+
+                assert_eq!(computer.run(), Err(expected));
+            }
+
+            #[test]
+            fn test_unknown_parameter_mode() {
+                let intcode: Vec<i64> = vec![301,0,0,0,99];
+                let expected = IntcodeError::new(0, 301, ErrorReason::UnknownParameterMode(3));
+
+                let mut computer = Computer::new(&intcode, vec![]);
+
+                assert_eq!(computer.next_operation(), Err(expected));
+            }
+
+            #[test]
+            fn test_negative_address() {
+                let intcode: Vec<i64> = vec![109,-5,2201,0,0,0,99];
+                let expected = IntcodeError::new(2, 2201, ErrorReason::NegativeAddress(-5));
+
+                let mut computer = Computer::new(&intcode, vec![]);
+
+                assert_eq!(computer.run(), Err(expected));
+            }
+
+            #[test]
+            fn test_negative_jump() {
+                let intcode: Vec<i64> = vec![1105,1,-1];
+                let expected = IntcodeError::new(0, 1105, ErrorReason::NegativeAddress(-1));
+
+                let mut computer = Computer::new(&intcode, vec![]);
+
+                assert_eq!(computer.run(), Err(expected));
+            }
+
+            #[test]
+            fn test_relative_base_overflow() {
+                let intcode: Vec<i64> = vec![109,9223372036854775807,109,1,99];
+                let expected = IntcodeError::new(2, 109, ErrorReason::Overflow);
+
+                let mut computer = Computer::new(&intcode, vec![]);
+
+                assert_eq!(computer.run(), Err(expected));
+            }
+
+            #[test]
+            fn test_huge_address() {
+                let intcode: Vec<i64> = vec![1101,5,6,1125899906842624,4,1125899906842624,99];
+
+                let mut computer = Computer::new(&intcode, vec![]);
+                computer.run().unwrap();
+
+                assert_eq!(computer.outputs, vec![11]);
+            }
+
+            #[test]
+            fn test_memory_limit() {
+                // writes 1 to every 4096th address until it runs out of memory
+                let intcode: Vec<i64> = vec![21101,1,0,100,109,4096,1105,1,0];
+                let expected = IntcodeError::new(0, 21101, ErrorReason::MemoryLimitExceeded(8192));
+
+                let mut computer = Computer::new(&intcode, vec![]);
+                computer.intcode.set_limit(Some(8192));
+
+                assert_eq!(computer.run(), Err(expected));
+                assert_eq!(computer.relative_base(), 8192);
+            }
+
+            #[test]
+            fn test_executed() {
+                let intcode: Vec<i64> = vec![3,9,4,9,1101,1,1,9,99,0];
+
+                let mut computer = Computer::new(&intcode, vec![]);
+                computer.run().unwrap();
+                assert_eq!(computer.executed(), 0);
+
+                computer.inputs.push_back(5);
+                computer.run().unwrap();
+                assert_eq!(computer.executed(), 4);
+            }
+
+            #[test]
+            fn test_step_budget() {
+                // counts up in address 7 forever
+                let intcode: Vec<i64> = vec![1001,7,1,7,1105,1,0];
+
+                let mut computer = Computer::new(&intcode, vec![]);
+                computer.set_budget(Budget::steps(10));
+
+                assert_eq!(computer.run(), Ok(RunState::BudgetExhausted));
+                assert_eq!(computer.run(), Ok(RunState::BudgetExhausted));
+                assert_eq!(computer.executed(), 10);
+                assert_eq!(computer.peek(7), 5);
+
+                computer.set_budget(Budget::steps(4));
+                assert_eq!(computer.run_until_output(), Ok(RunState::BudgetExhausted));
+                assert_eq!(computer.executed(), 14);
+                assert_eq!(computer.peek(7), 7);
+            }
+
+            #[test]
+            fn test_time_budget() {
+                let intcode: Vec<i64> = vec![1105,1,0];
+
+                let mut computer = Computer::new(&intcode, vec![]);
+                computer.set_budget(Budget::time(Duration::from_millis(10)));
+
+                assert_eq!(computer.run(), Ok(RunState::BudgetExhausted));
+                assert!(computer.executed() > 0);
+            }
+
+            #[test]
+            fn test_budget_does_not_stop_finished_programs() {
+                let intcode: Vec<i64> = vec![1101,1,1,0,99];
+
+                let mut computer = Computer::new(&intcode, vec![]);
+                computer.set_budget(Budget::steps(2).with_time(Duration::from_secs(60)));
+
+                assert_eq!(computer.run(), Ok(RunState::Halted));
+            }
+
+            #[test]
+            fn test_running_after_halt_does_nothing() {
+                let intcode: Vec<i64> = vec![1101,1,1,0,99];
+
+                let mut computer = Computer::new(&intcode, vec![]);
+                computer.set_budget(Budget::steps(2));
+
+                assert_eq!(computer.run(), Ok(RunState::Halted));
+                assert_eq!(computer.executed(), 2);
+                assert_eq!(computer.run(), Ok(RunState::Halted));
+                assert_eq!(computer.next_operation(), Ok(RunState::Halted));
+                assert_eq!(computer.executed(), 2);
+            }
+
+            #[test]
+            fn test_self_modifying_code() {
+                // adds 5 and 6, then rewrites the ADD at address 0 to a MUL and runs it again
+                let intcode: Vec<i64> = vec![1101,5,6,30,4,30,1008,0,1102,31,1005,31,20,1101,1102,0,0,1105,1,0,99];
+
+                for cache in &[true, false] {
+                    let mut computer = Computer::new(&intcode, vec![]);
+                    computer.set_decode_cache(*cache);
+                    computer.run().unwrap();
+
+                    assert_eq!(computer.outputs, vec![11, 30]);
+                }
+            }
+
+            #[test]
+            fn test_step_back() {
+                let intcode: Vec<i64> = vec![1101,5,6,30,4,30,1008,0,1102,31,1005,31,20,1101,1102,0,0,1105,1,0,99];
+                let mut computer = Computer::new(&intcode, vec![]);
+                computer.set_history(Some(100));
+                computer.run().unwrap();
+                let executed = computer.executed();
+
+                let mut records = vec![];
+                while let Some(record) = computer.step_back() {
+                    records.push(record);
+                }
+
+                assert_eq!(records.len() as u64, executed);
+                assert_eq!(records.iter().filter_map(|record| record.output).collect::<Vec<i64>>(), vec![30, 11]);
+                assert_eq!(computer.intcode, intcode);
+                assert_eq!((computer.ptr(), computer.relative_base(), computer.executed()), (0, 0, 0));
+
+                computer.outputs.clear();
+                computer.run().unwrap();
+                assert_eq!(computer.outputs, vec![11, 30]);
+            }
+
+            #[test]
+            fn test_history_limit() {
+                let intcode: Vec<i64> = vec![3,9,109,4,1001,9,1,9,99,0];
+                let mut computer = Computer::new(&intcode, vec![7]);
+                computer.set_history(Some(2));
+                computer.run().unwrap();
+
+                assert_eq!(computer.history_len(), 2);
+                computer.step_back();
+                assert_eq!(computer.step_back().map(|record| record.writes), Some(vec![(9, 7)]));
+                assert_eq!(computer.step_back(), None);
+                assert_eq!((computer.ptr(), computer.relative_base(), computer.peek(9)), (4, 4, 7));
+            }
+        }
+        )* };
+    }
+
+    backend_tests!(interpreter: IntCodeComputer, compiled: CompiledComputer);
+}
@@ -0,0 +1,181 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::disassembler::disassemble;
+use super::trace::{TraceEvent, TraceSink};
+
+/// How often one address was used.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Counts {
+    /// Times an instruction starting here was executed.
+    pub executed: u64,
+    /// Times a parameter read this address as data.
+    pub read: u64,
+    pub written: u64,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Totals {
+    pub instructions: u64,
+    pub executed_addresses: usize,
+    pub read_addresses: usize,
+    pub written_addresses: usize,
+    /// Addresses written that were also fetched as part of an executed instruction.
+    pub self_modified: usize,
+}
+
+/// Counts what every executed instruction did to memory. Give it to a machine
+/// with `set_tracer`, wrapped in `Arc<Mutex<_>>` to read it afterwards.
+#[derive(Debug, Default, Clone)]
+pub struct Coverage {
+    counts: BTreeMap<usize, Counts>,
+    /// Every address fetched as an opcode or parameter word.
+    code: BTreeSet<usize>,
+    instructions: u64,
+}
+
+impl Coverage {
+    pub fn new() -> Coverage {
+        return Coverage::default();
+    }
+
+    /// Unused addresses count as zero.
+    pub fn counts(&self, address: usize) -> Counts {
+        return self.counts.get(&address).cloned().unwrap_or_default();
+    }
+
+    pub fn is_self_modified(&self, address: usize) -> bool {
+        return self.code.contains(&address) && self.counts(address).written > 0;
+    }
+
+    pub fn totals(&self) -> Totals {
+        let used = |count: fn(&Counts) -> u64| self.counts.values().filter(|counts| count(counts) > 0).count();
+        return Totals {
+            instructions: self.instructions,
+            executed_addresses: used(|counts| counts.executed),
+            read_addresses: used(|counts| counts.read),
+            written_addresses: used(|counts| counts.written),
+            self_modified: self.code.iter().filter(|address| self.is_self_modified(**address)).count(),
+        };
+    }
+
+    /// The disassembly of `intcode` with executed, read and written counts for
+    /// each line, followed by the totals. Lines containing self-modified
+    /// addresses are marked with `*`. Pass the memory as it was after the run
+    /// to see code the program wrote for itself.
+    pub fn report(&self, intcode: &[i64]) -> String {
+        let mut report = format!("{:>8} {:>8} {:>8}\n", "exec", "read", "write");
+        let mut instructions = 0;
+        let mut covered = 0;
+        for line in disassemble(intcode) {
+            let cells = line.address()..line.address() + line.size();
+            let mut sum = Counts::default();
+            for address in cells.clone() {
+                let counts = self.counts(address);
+                sum.executed += counts.executed;
+                sum.read += counts.read;
+                sum.written += counts.written;
+            }
+            if line.size() > 1 || sum.executed > 0 {
+                instructions += 1;
+                covered += (sum.executed > 0) as usize;
+            }
+            let marker = if cells.clone().any(|address| self.is_self_modified(address)) { '*' } else { ' ' };
+            report += &format!("{:>8} {:>8} {:>8} {}{}\n", column(sum.executed), column(sum.read), column(sum.written), marker, line);
+        }
+
+        let totals = self.totals();
+        report += &format!("\n{} instructions executed\n", totals.instructions);
+        report += &format!("{} of {} instructions covered\n", covered, instructions);
+        report += &format!("{} addresses read, {} written, {} self-modified\n",
+            totals.read_addresses, totals.written_addresses, totals.self_modified);
+        return report;
+    }
+}
+
+fn column(count: u64) -> String {
+    return if count == 0 { "-".to_owned() } else { count.to_string() };
+}
+
+impl<C> TraceSink<C> for Coverage {
+    fn record(&mut self, event: &TraceEvent<C>) {
+        self.instructions += 1;
+        self.counts.entry(event.ptr).or_default().executed += 1;
+        self.code.extend(event.ptr..=event.ptr + event.parameters.len());
+        for address in &event.reads {
+            self.counts.entry(*address).or_default().read += 1;
+        }
+        for write in &event.writes {
+            self.counts.entry(write.address).or_default().written += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use super::super::computer::IntCodeComputer;
+
+    fn run(intcode: &[i64], inputs: Vec<i64>) -> (Coverage, Vec<i64>) {
+        let coverage = Arc::new(Mutex::new(Coverage::new()));
+        let mut computer = IntCodeComputer::new(intcode, inputs);
+        computer.set_tracer(Box::new(coverage.clone()));
+        computer.run().unwrap();
+
+        let memory = computer.intcode.segments()[0].1.to_vec();
+        let coverage = coverage.lock().unwrap().clone();
+        return (coverage, memory);
+    }
+
+    #[test]
+    fn test_counts() {
+        // outputs address 10 counting down from 3, then halts
+        let intcode = vec![4,10,1001,10,-1,10,1005,10,0,99,3];
+
+        let (coverage, _) = run(&intcode, vec![]);
+
+        assert_eq!(coverage.counts(0), Counts { executed: 3, read: 0, written: 0 });
+        assert_eq!(coverage.counts(10), Counts { executed: 0, read: 9, written: 3 });
+        assert_eq!(coverage.counts(11), Counts::default());
+        assert_eq!(coverage.totals(), Totals {
+            instructions: 10,
+            executed_addresses: 4,
+            read_addresses: 1,
+            written_addresses: 1,
+            self_modified: 0,
+        });
+    }
+
+    #[test]
+    fn test_report() {
+        // rewrites the ADD at address 4 into a HLT before reaching it
+        let intcode = vec![1101,0,99,4,1,0,0,0];
+        let expected = "    exec     read    write
+       1        -        -      0: ADD   #0, #99, 4
+       1        -        1 *    4: HLT
+       -        -        -      5: .data 0
+       -        -        -      6: .data 0
+       -        -        -      7: .data 0
+
+2 instructions executed
+2 of 2 instructions covered
+0 addresses read, 1 written, 1 self-modified
+";
+
+        let (coverage, memory) = run(&intcode, vec![]);
+
+        assert_eq!(coverage.report(&memory), expected);
+    }
+
+    #[test]
+    fn test_boost_self_test() {
+        let intcode = super::super::parse(include_str!("../../resources/9.input"));
+
+        let (coverage, _) = run(&intcode, vec![1]);
+        let totals = coverage.totals();
+
+        assert!(totals.executed_addresses > 100);
+        assert_eq!(totals.self_modified, 0);
+    }
+}
@@ -0,0 +1,553 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::io::{BufRead, Write};
+
+use super::computer::{IntCodeComputer, RunState, operation_from_opcode};
+use super::opcodes::OpcodeTable;
+use super::disassembler::{decode_with, Line};
+use super::error::IntcodeError;
+use super::history::UndoRecord;
+
+/// Instructions the debugger can step back through.
+const HISTORY_LIMIT: usize = 1_000_000;
+
+const HELP: &str = "\
+Commands:
+  s, step [n]            execute n instructions (default 1)
+  c, continue            run until a breakpoint, watchpoint, input request or halt
+  bs, back [n]           undo n instructions (default 1)
+  rc, rcontinue          run backwards to a breakpoint or the start of the history
+  who <addr>             run back to the instruction that last wrote addr
+  b, break <addr>        break when ptr reaches addr
+  b, break op <op>       break before any instruction with this operation (ADD or 1)
+  d, delete <addr>       remove an address breakpoint
+  d, delete op <op>      remove an operation breakpoint
+  w, watch <addr>        stop when the value at addr changes
+  unwatch <addr>         remove a watchpoint
+  info                   list breakpoints and watchpoints
+  r, regs                show ptr and relative_base
+  io                     show pending inputs and outputs
+  input <v>...           queue input values
+  x, mem <addr> [count]  print memory from addr (default 8 cells)
+  poke <addr> <value>    write value to addr
+  l, list [n]            disassemble n instructions from ptr (default 5)
+  h, help                show this help
+  q, quit                exit the debugger";
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Stop {
+    Step(RunState),
+    Breakpoint(usize),
+    OpcodeBreakpoint(i64),
+    Watchpoint { address: usize, old: i64, new: i64 },
+    /// Running backwards used up the recorded history.
+    StartOfHistory,
+}
+
+pub struct Debugger {
+    pub computer: IntCodeComputer,
+    breakpoints: BTreeSet<usize>,
+    opcode_breakpoints: BTreeSet<i64>,
+    watchpoints: BTreeMap<usize, i64>,
+    outputs_seen: usize,
+}
+
+impl Debugger {
+    pub fn new(mut computer: IntCodeComputer) -> Debugger {
+        computer.set_history(Some(HISTORY_LIMIT));
+        return Debugger {
+            computer,
+            breakpoints: BTreeSet::new(),
+            opcode_breakpoints: BTreeSet::new(),
+            watchpoints: BTreeMap::new(),
+            outputs_seen: 0,
+        };
+    }
+
+    pub fn add_breakpoint(&mut self, address: usize) {
+        self.breakpoints.insert(address);
+    }
+
+    pub fn add_opcode_breakpoint(&mut self, operation: i64) {
+        self.opcode_breakpoints.insert(operation);
+    }
+
+    pub fn add_watchpoint(&mut self, address: usize) {
+        let value = self.computer.peek(address);
+        self.watchpoints.insert(address, value);
+    }
+
+    /// Executes a single instruction, reporting a watchpoint if one fired.
+    pub fn step(&mut self) -> Result<Stop, IntcodeError> {
+        let state = self.computer.next_operation()?;
+
+        let mut stop = Stop::Step(state);
+        for (address, old) in self.watchpoints.iter_mut() {
+            let new = self.computer.peek(*address);
+            if new != *old {
+                if let Stop::Step(_) = stop {
+                    stop = Stop::Watchpoint { address: *address, old: *old, new };
+                }
+                *old = new;
+            }
+        }
+
+        return Ok(stop);
+    }
+
+    /// Runs until something needs attention. Always executes at least one
+    /// instruction so that continuing from a breakpoint makes progress.
+    pub fn continue_execution(&mut self) -> Result<Stop, IntcodeError> {
+        loop {
+            match self.step()? {
+                Stop::Step(RunState::Running) | Stop::Step(RunState::OutputReady) => (),
+                stop => return Ok(stop),
+            }
+
+            let ptr = self.computer.ptr();
+            if self.breakpoints.contains(&ptr) {
+                return Ok(Stop::Breakpoint(ptr));
+            }
+            let operation = operation_from_opcode(self.computer.peek(ptr));
+            if self.opcode_breakpoints.contains(&operation) {
+                return Ok(Stop::OpcodeBreakpoint(operation));
+            }
+        }
+    }
+
+    /// Undoes the last instruction, including its input and output.
+    pub fn step_back(&mut self) -> Option<UndoRecord> {
+        let record = self.computer.step_back()?;
+        if let Some(input) = record.input {
+            self.computer.inputs.push_front(input);
+        }
+        if record.output.is_some() {
+            self.computer.outputs.pop();
+            self.outputs_seen = self.outputs_seen.min(self.computer.outputs.len());
+        }
+        for (address, value) in self.watchpoints.iter_mut() {
+            *value = self.computer.peek(*address);
+        }
+
+        return Some(record);
+    }
+
+    /// Steps back until ptr reaches a breakpoint. Always undoes at least one
+    /// instruction so that reversing from a breakpoint makes progress.
+    pub fn reverse_continue(&mut self) -> Stop {
+        while self.step_back().is_some() {
+            let ptr = self.computer.ptr();
+            if self.breakpoints.contains(&ptr) {
+                return Stop::Breakpoint(ptr);
+            }
+            let operation = operation_from_opcode(self.computer.peek(ptr));
+            if self.opcode_breakpoints.contains(&operation) {
+                return Stop::OpcodeBreakpoint(operation);
+            }
+        }
+
+        return Stop::StartOfHistory;
+    }
+
+    /// Steps back to just before the most recent recorded write to `address`,
+    /// leaving ptr on the instruction that made it. Reports the write as a
+    /// watchpoint would have, or returns `None` without moving if no recorded
+    /// instruction wrote there.
+    pub fn back_to_write(&mut self, address: usize) -> Option<Stop> {
+        let steps = self.computer.steps_since_write(address)?;
+        for _ in 1..steps {
+            self.step_back();
+        }
+        let new = self.computer.peek(address);
+        self.step_back();
+
+        return Some(Stop::Watchpoint { address, old: self.computer.peek(address), new });
+    }
+
+    pub fn repl<R: BufRead, W: Write>(&mut self, input: R, output: &mut W) -> io::Result<()> {
+        writeln!(output, "Intcode debugger. Type 'help' for commands.")?;
+        self.print_current(output)?;
+        write!(output, "debug> ")?;
+        output.flush()?;
+
+        for line in input.lines() {
+            if !self.execute(&line?, output)? {
+                break;
+            }
+            write!(output, "debug> ")?;
+            output.flush()?;
+        }
+
+        return Ok(());
+    }
+
+    /// Runs one debugger command. Returns false when the session should end.
+    pub fn execute<W: Write>(&mut self, command: &str, output: &mut W) -> io::Result<bool> {
+        let words: Vec<&str> = command.split_whitespace().collect();
+        if words.is_empty() {
+            return Ok(true);
+        }
+
+        let args = &words[1..];
+        match words[0] {
+            "s" | "step" => {
+                let count = match args.first() {
+                    Some(count) => match count.parse::<usize>() {
+                        Ok(count) => count,
+                        Err(_) => return usage(output, "step [n]"),
+                    },
+                    None => 1,
+                };
+                for _ in 0..count {
+                    let result = self.step();
+                    let stop = self.report(result, output)?;
+                    if stop != Some(Stop::Step(RunState::Running)) && stop != Some(Stop::Step(RunState::OutputReady)) {
+                        break;
+                    }
+                }
+                self.print_current(output)?;
+            },
+            "c" | "continue" => {
+                let result = self.continue_execution();
+                self.report(result, output)?;
+                self.print_current(output)?;
+            },
+            "bs" | "back" => {
+                let count = match args.first() {
+                    Some(count) => match count.parse::<usize>() {
+                        Ok(count) => count,
+                        Err(_) => return usage(output, "back [n]"),
+                    },
+                    None => 1,
+                };
+                for _ in 0..count {
+                    if self.step_back().is_none() {
+                        self.report(Ok(Stop::StartOfHistory), output)?;
+                        break;
+                    }
+                }
+                self.print_current(output)?;
+            },
+            "rc" | "rcontinue" => {
+                let stop = self.reverse_continue();
+                self.report(Ok(stop), output)?;
+                self.print_current(output)?;
+            },
+            "who" => match parse_address(args.first()) {
+                Some(address) => match self.back_to_write(address) {
+                    Some(stop) => {
+                        self.report(Ok(stop), output)?;
+                        self.print_current(output)?;
+                    },
+                    None => writeln!(output, "No recorded write to {}", address)?,
+                },
+                None => return usage(output, "who <addr>"),
+            },
+            "b" | "break" => match parse_target(self.computer.opcodes(), args) {
+                Some(Target::Address(address)) => {
+                    self.add_breakpoint(address);
+                    writeln!(output, "Breakpoint at {}", address)?;
+                },
+                Some(Target::Operation(operation)) => {
+                    self.add_opcode_breakpoint(operation);
+                    writeln!(output, "Breakpoint on {}", operation_name(self.computer.opcodes(), operation))?;
+                },
+                None => return usage(output, "break <addr> | break op <op>"),
+            },
+            "d" | "delete" => match parse_target(self.computer.opcodes(), args) {
+                Some(Target::Address(address)) => {
+                    self.breakpoints.remove(&address);
+                },
+                Some(Target::Operation(operation)) => {
+                    self.opcode_breakpoints.remove(&operation);
+                },
+                None => return usage(output, "delete <addr> | delete op <op>"),
+            },
+            "w" | "watch" => match parse_address(args.first()) {
+                Some(address) => {
+                    self.add_watchpoint(address);
+                    writeln!(output, "Watching {} (currently {})", address, self.computer.peek(address))?;
+                },
+                None => return usage(output, "watch <addr>"),
+            },
+            "unwatch" => match parse_address(args.first()) {
+                Some(address) => {
+                    self.watchpoints.remove(&address);
+                },
+                None => return usage(output, "unwatch <addr>"),
+            },
+            "info" => {
+                let operations: Vec<&str> = self.opcode_breakpoints.iter().map(|operation| operation_name(self.computer.opcodes(), *operation)).collect();
+                writeln!(output, "Breakpoints: {:?}", self.breakpoints)?;
+                writeln!(output, "Operation breakpoints: {:?}", operations)?;
+                writeln!(output, "Watchpoints: {:?}", self.watchpoints.keys().collect::<Vec<_>>())?;
+            },
+            "r" | "regs" => {
+                writeln!(output, "ptr: {}, relative_base: {}", self.computer.ptr(), self.computer.relative_base())?;
+            },
+            "io" => {
+                writeln!(output, "Pending inputs: {:?}", self.computer.inputs)?;
+                writeln!(output, "Outputs: {:?}", self.computer.outputs)?;
+            },
+            "input" => {
+                let values: Result<Vec<i64>, _> = args.iter().map(|value| value.parse::<i64>()).collect();
+                match values {
+                    Ok(values) if !values.is_empty() => self.computer.inputs.extend(values),
+                    _ => return usage(output, "input <v>..."),
+                }
+            },
+            "x" | "mem" => {
+                let start = match parse_address(args.first()) {
+                    Some(start) => start,
+                    None => return usage(output, "mem <addr> [count]"),
+                };
+                let count = args.get(1).and_then(|count| count.parse::<usize>().ok()).unwrap_or(8);
+                self.print_memory(start, count, output)?;
+            },
+            "poke" => {
+                let value = args.get(1).and_then(|value| value.parse::<i64>().ok());
+                match (parse_address(args.first()), value) {
+                    (Some(address), Some(value)) => {
+                        if let Err(error) = self.computer.poke(address, value) {
+                            writeln!(output, "{}", error)?;
+                        }
+                    },
+                    _ => return usage(output, "poke <addr> <value>"),
+                }
+            },
+            "l" | "list" => {
+                let count = args.first().and_then(|count| count.parse::<usize>().ok()).unwrap_or(5);
+                let mut address = self.computer.ptr();
+                for _ in 0..count {
+                    let line = self.line_at(address);
+                    writeln!(output, "{}", line)?;
+                    address += line.size();
+                }
+            },
+            "h" | "help" => writeln!(output, "{}", HELP)?,
+            "q" | "quit" => return Ok(false),
+            unknown => writeln!(output, "Unknown command '{}'. Type 'help' for commands.", unknown)?,
+        }
+
+        return Ok(true);
+    }
+
+    fn report<W: Write>(&mut self, result: Result<Stop, IntcodeError>, output: &mut W) -> io::Result<Option<Stop>> {
+        for value in &self.computer.outputs[self.outputs_seen..] {
+            writeln!(output, "Output: {}", value)?;
+        }
+        self.outputs_seen = self.computer.outputs.len();
+
+        let stop = match result {
+            Ok(stop) => stop,
+            Err(error) => {
+                writeln!(output, "Fault: {}", error)?;
+                return Ok(None);
+            },
+        };
+        match stop {
+            Stop::Step(RunState::AwaitingInput) => writeln!(output, "Waiting for input")?,
+            Stop::Step(RunState::Halted) => writeln!(output, "Halted")?,
+            Stop::Step(_) => (),
+            Stop::Breakpoint(address) => writeln!(output, "Breakpoint at {}", address)?,
+            Stop::OpcodeBreakpoint(operation) => writeln!(output, "Breakpoint on {}", operation_name(self.computer.opcodes(), operation))?,
+            Stop::Watchpoint { address, old, new } => writeln!(output, "Watchpoint {}: {} -> {}", address, old, new)?,
+            Stop::StartOfHistory => writeln!(output, "Start of recorded history")?,
+        }
+
+        return Ok(Some(stop));
+    }
+
+    /// Decodes the instruction at `address` from the four cells it can span.
+    fn line_at(&self, address: usize) -> Line {
+        let window: Vec<i64> = (0..4).map(|offset| self.computer.peek(address.saturating_add(offset))).collect();
+        return match decode_with(&window, 0, |operation| self.computer.opcodes().info(operation)) {
+            Some(Line::Instruction { mnemonic, operands, .. }) => Line::Instruction { address, mnemonic, operands },
+            _ => Line::Data { address, value: window[0] },
+        };
+    }
+
+    fn print_current<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        return writeln!(output, "{}", self.line_at(self.computer.ptr()));
+    }
+
+    fn print_memory<W: Write>(&self, start: usize, count: usize, output: &mut W) -> io::Result<()> {
+        let last = start.saturating_add(count);
+        let mut address = start;
+        while address < last {
+            let end = usize::min(address.saturating_add(8), last);
+            let values: Vec<String> = (address..end).map(|address| self.computer.peek(address).to_string()).collect();
+            writeln!(output, "{:>5}: {}", address, values.join(" "))?;
+            address = end;
+        }
+
+        return Ok(());
+    }
+}
+
+enum Target {
+    Address(usize),
+    Operation(i64),
+}
+
+fn parse_target(opcodes: &OpcodeTable, args: &[&str]) -> Option<Target> {
+    if args.first() == Some(&"op") {
+        let operation = args.get(1)?;
+        return match operation.parse::<i64>() {
+            Ok(operation) => Some(Target::Operation(operation)),
+            Err(_) => opcodes.operation(operation).map(Target::Operation),
+        };
+    }
+
+    return parse_address(args.first()).map(Target::Address);
+}
+
+fn parse_address(arg: Option<&&str>) -> Option<usize> {
+    return arg?.parse::<usize>().ok();
+}
+
+fn operation_name(opcodes: &OpcodeTable, operation: i64) -> &'static str {
+    return match opcodes.info(operation) {
+        Some((mnemonic, _)) => mnemonic,
+        None => "unknown operation",
+    };
+}
+
+fn usage<W: Write>(output: &mut W, usage: &str) -> io::Result<bool> {
+    writeln!(output, "Usage: {}", usage)?;
+    return Ok(true);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_commands(debugger: &mut Debugger, commands: &[&str]) -> String {
+        let mut output: Vec<u8> = vec![];
+        for command in commands {
+            debugger.execute(command, &mut output).unwrap();
+        }
+        return String::from_utf8(output).unwrap();
+    }
+
+    #[test]
+    fn test_step_and_registers() {
+        let intcode = vec![109,19,204,-34,99];
+        let mut debugger = Debugger::new(IntCodeComputer::new(&intcode, vec![]));
+
+        let output = run_commands(&mut debugger, &["step", "regs"]);
+
+        assert_eq!(output, "    2: OUT   @-34\nptr: 2, relative_base: 19\n");
+    }
+
+    #[test]
+    fn test_breakpoint() {
+        let intcode = vec![1101,1,1,20,1101,2,2,21,1101,3,3,22,99];
+        let mut debugger = Debugger::new(IntCodeComputer::new(&intcode, vec![]));
+        debugger.add_breakpoint(8);
+
+        assert_eq!(debugger.continue_execution(), Ok(Stop::Breakpoint(8)));
+        assert_eq!(debugger.computer.peek(21), 4);
+        assert_eq!(debugger.computer.peek(22), 0);
+        assert_eq!(debugger.continue_execution(), Ok(Stop::Step(RunState::Halted)));
+    }
+
+    #[test]
+    fn test_opcode_breakpoint() {
+        let intcode = vec![1101,1,1,20,1102,2,2,21,99];
+        let mut debugger = Debugger::new(IntCodeComputer::new(&intcode, vec![]));
+
+        run_commands(&mut debugger, &["break op MUL"]);
+
+        assert_eq!(debugger.continue_execution(), Ok(Stop::OpcodeBreakpoint(2)));
+        assert_eq!(debugger.computer.ptr(), 4);
+    }
+
+    #[test]
+    fn test_watchpoint() {
+        let intcode = vec![1101,1,1,20,1101,2,2,21,1101,3,3,20,99];
+        let mut debugger = Debugger::new(IntCodeComputer::new(&intcode, vec![]));
+        debugger.add_watchpoint(20);
+
+        assert_eq!(debugger.continue_execution(), Ok(Stop::Watchpoint { address: 20, old: 0, new: 2 }));
+        assert_eq!(debugger.continue_execution(), Ok(Stop::Watchpoint { address: 20, old: 2, new: 6 }));
+        assert_eq!(debugger.computer.ptr(), 12);
+    }
+
+    #[test]
+    fn test_input_poke_and_memory() {
+        let intcode = vec![3,7,4,7,4,8,99,0,0];
+        let mut debugger = Debugger::new(IntCodeComputer::new(&intcode, vec![]));
+
+        let output = run_commands(&mut debugger, &["continue", "input 5", "poke 8 42", "mem 6 3", "continue", "io"]);
+
+        let expected = "Waiting for input\n    0: IN    7\n\
+                        \x20   6: 99 0 42\n\
+                        Output: 5\nOutput: 42\nHalted\n    6: HLT\n\
+                        Pending inputs: []\nOutputs: [5, 42]\n";
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_back_restores_io() {
+        let intcode = vec![3,7,4,7,4,8,99,0,0];
+        let mut debugger = Debugger::new(IntCodeComputer::new(&intcode, vec![5]));
+
+        let output = run_commands(&mut debugger, &["step 2", "back 2", "io", "back"]);
+
+        let expected = "Output: 5\n    4: OUT   8\n    0: IN    7\n\
+                        Pending inputs: [5]\nOutputs: []\n\
+                        Start of recorded history\n    0: IN    7\n";
+        assert_eq!(output, expected);
+        assert_eq!(debugger.computer.peek(7), 0);
+    }
+
+    #[test]
+    fn test_who_wrote() {
+        let intcode = vec![1101,1,1,20,1101,2,2,21,1101,3,3,20,99];
+        let mut debugger = Debugger::new(IntCodeComputer::new(&intcode, vec![]));
+        debugger.continue_execution().unwrap();
+
+        let output = run_commands(&mut debugger, &["who 21", "who 21", "who 20"]);
+
+        let expected = "Watchpoint 21: 0 -> 4\n    4: ADD   #2, #2, 21\n\
+                        No recorded write to 21\n\
+                        Watchpoint 20: 0 -> 2\n    0: ADD   #1, #1, 20\n";
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_reverse_continue() {
+        let intcode = vec![1101,1,1,20,1101,2,2,21,1101,3,3,22,99];
+        let mut debugger = Debugger::new(IntCodeComputer::new(&intcode, vec![]));
+        debugger.add_breakpoint(4);
+        debugger.computer.run().unwrap();
+
+        assert_eq!(debugger.reverse_continue(), Stop::Breakpoint(4));
+        assert_eq!(debugger.computer.peek(21), 0);
+        assert_eq!(debugger.computer.peek(20), 2);
+        assert_eq!(debugger.reverse_continue(), Stop::StartOfHistory);
+    }
+
+    #[test]
+    fn test_far_jump_and_memory() {
+        let intcode = vec![1105,1,1000000000000];
+        let mut debugger = Debugger::new(IntCodeComputer::new(&intcode, vec![]));
+
+        let output = run_commands(&mut debugger, &["step", "list 1", "mem 18446744073709551610"]);
+
+        let expected = "1000000000000: .data 0\n1000000000000: .data 0\n\
+                        18446744073709551610: 0 0 0 0 0\n";
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_fault_is_reported() {
+        let intcode = vec![42];
+        let mut debugger = Debugger::new(IntCodeComputer::new(&intcode, vec![]));
+
+        let output = run_commands(&mut debugger, &["step"]);
+
+        assert_eq!(output, "Fault: unknown opcode at ptr 0 (opcode 42)\n    0: .data 42\n");
+    }
+}
@@ -0,0 +1,96 @@
+use super::memory::DENSE_LIMIT;
+
+/// An opcode split into its operation and the modes of up to three parameters.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Instruction {
+    pub opcode: i64,
+    pub operation: i64,
+    pub modes: [i64; 3],
+}
+
+impl Instruction {
+    pub fn decode(opcode: i64) -> Instruction {
+        let params = opcode / 100;
+        return Instruction {
+            opcode,
+            operation: opcode % 100,
+            modes: [params % 10, params / 10 % 10, params / 100 % 10],
+        };
+    }
+}
+
+/// Decoded instructions by address, for addresses in the dense region.
+/// Writes through the machine invalidate the entry for the address written,
+/// and each entry also remembers the opcode it came from, so memory changed
+/// any other way is decoded again rather than run stale.
+#[derive(Debug, Clone)]
+pub(crate) struct DecodeCache {
+    entries: Vec<Option<Instruction>>,
+    enabled: bool,
+}
+
+impl DecodeCache {
+    pub(crate) fn new() -> DecodeCache {
+        return DecodeCache { entries: vec![], enabled: true };
+    }
+
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.clear();
+    }
+
+    pub(crate) fn decode(&mut self, address: usize, opcode: i64) -> Instruction {
+        if !self.enabled || address >= DENSE_LIMIT {
+            return Instruction::decode(opcode);
+        }
+
+        if let Some(Some(instruction)) = self.entries.get(address) {
+            if instruction.opcode == opcode {
+                return *instruction;
+            }
+        }
+        if address >= self.entries.len() {
+            self.entries.resize(address + 1, None);
+        }
+        let instruction = Instruction::decode(opcode);
+        self.entries[address] = Some(instruction);
+        return instruction;
+    }
+
+    pub(crate) fn invalidate(&mut self, address: usize) {
+        if let Some(entry) = self.entries.get_mut(address) {
+            *entry = None;
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::computer::modes_from_opcode;
+
+    #[test]
+    fn test_decode_matches_modes_from_opcode() {
+        for opcode in &[1, 99, 1002, 21101, 109, 204, 11107, 1105, -1, 221199] {
+            let instruction = Instruction::decode(*opcode);
+            assert_eq!(instruction.operation, opcode % 100);
+            assert_eq!(instruction.modes.to_vec(), modes_from_opcode(*opcode, 3), "\nFailed on {}\n", opcode);
+        }
+    }
+
+    #[test]
+    fn test_cache_redecodes_changed_opcodes() {
+        let mut cache = DecodeCache::new();
+
+        assert_eq!(cache.decode(4, 1002).modes, [0, 1, 0]);
+        assert_eq!(cache.decode(4, 1101).modes, [1, 1, 0]);
+        cache.invalidate(4);
+        assert_eq!(cache.entries[4], None);
+        assert_eq!(cache.decode(DENSE_LIMIT, 99).operation, 99);
+        assert_eq!(cache.entries.len(), 5);
+    }
+}
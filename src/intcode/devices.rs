@@ -0,0 +1,317 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use super::cell::Cell;
+use super::error::ErrorReason;
+
+/// Something mapped over a range of a machine's memory with
+/// `IntCodeComputer::map_device`. Offsets count from the start of the range.
+pub trait Device<C = i64> {
+    /// A program reading the cell at `offset`.
+    fn read(&mut self, offset: usize) -> Result<C, ErrorReason>;
+
+    /// What the cell at `offset` holds, without the side effects of `read`,
+    /// for debuggers and traces.
+    fn peek(&self, offset: usize) -> C;
+
+    fn write(&mut self, offset: usize, value: C) -> Result<(), ErrorReason>;
+
+    /// Called after each instruction the machine executes.
+    fn tick(&mut self) {}
+}
+
+/// Lets the caller keep a handle on a device after mapping it.
+impl<C, T: Device<C>> Device<C> for Arc<Mutex<T>> {
+    fn read(&mut self, offset: usize) -> Result<C, ErrorReason> {
+        return self.lock().unwrap().read(offset);
+    }
+
+    fn peek(&self, offset: usize) -> C {
+        return self.lock().unwrap().peek(offset);
+    }
+
+    fn write(&mut self, offset: usize, value: C) -> Result<(), ErrorReason> {
+        return self.lock().unwrap().write(offset, value);
+    }
+
+    fn tick(&mut self) {
+        self.lock().unwrap().tick();
+    }
+}
+
+/// A character terminal over two cells. Writing a character code to the
+/// first prints it, and reading it takes the next typed character, or 0 if
+/// there is none. The second reads as the number of characters waiting.
+#[derive(Debug, Default, Clone)]
+pub struct Console {
+    typed: VecDeque<u8>,
+    printed: String,
+}
+
+impl Console {
+    pub const SIZE: usize = 2;
+
+    pub fn new() -> Console {
+        return Console::default();
+    }
+
+    /// Queues characters for the program to read.
+    pub fn type_text(&mut self, text: &str) {
+        self.typed.extend(text.bytes());
+    }
+
+    /// Everything the program has printed so far.
+    pub fn printed(&self) -> &str {
+        return &self.printed;
+    }
+}
+
+impl<C: Cell> Device<C> for Console {
+    fn read(&mut self, offset: usize) -> Result<C, ErrorReason> {
+        if offset == 0 {
+            return Ok(C::from_i64(self.typed.pop_front().unwrap_or(0) as i64));
+        }
+        return Ok(self.peek(offset));
+    }
+
+    fn peek(&self, offset: usize) -> C {
+        return match offset {
+            0 => C::from_i64(self.typed.front().cloned().unwrap_or(0) as i64),
+            _ => C::from_i64(self.typed.len() as i64),
+        };
+    }
+
+    /// Writes to the count are ignored.
+    fn write(&mut self, offset: usize, value: C) -> Result<(), ErrorReason> {
+        if offset == 0 {
+            match value.to_i64() {
+                Some(code @ 0..=255) => self.printed.push(code as u8 as char),
+                _ => return Err(ErrorReason::OutOfRange),
+            }
+        }
+        return Ok(());
+    }
+}
+
+/// A grid of pixels in day 8's colours, one cell per pixel, row by row.
+/// Every pixel starts transparent. Reading or writing past the last pixel
+/// faults with `ErrorReason::OutOfRange`, and peeks there see 0.
+#[derive(Debug, Clone)]
+pub struct Framebuffer {
+    width: usize,
+    pixels: Vec<u8>,
+}
+
+impl Framebuffer {
+    pub const BLACK: u8 = 0;
+    pub const WHITE: u8 = 1;
+    pub const TRANSPARENT: u8 = 2;
+
+    /// Panics if `width` is 0.
+    pub fn new(width: usize, height: usize) -> Framebuffer {
+        assert!(width > 0, "A framebuffer needs at least one column");
+        return Framebuffer { width, pixels: vec![Framebuffer::TRANSPARENT; width * height] };
+    }
+
+    /// Cells to map, one per pixel.
+    pub fn size(&self) -> usize {
+        return self.pixels.len();
+    }
+
+    /// One line per row, drawn the way day 8 displays its image.
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        for row in self.pixels.chunks(self.width) {
+            text.extend(row.iter().map(|pixel| match *pixel {
+                Framebuffer::BLACK => ' ',
+                Framebuffer::WHITE => 'W',
+                _ => 'T',
+            }));
+            text.push('\n');
+        }
+        return text;
+    }
+}
+
+impl<C: Cell> Device<C> for Framebuffer {
+    fn read(&mut self, offset: usize) -> Result<C, ErrorReason> {
+        let pixel = self.pixels.get(offset).ok_or(ErrorReason::OutOfRange)?;
+        return Ok(C::from_i64(*pixel as i64));
+    }
+
+    fn peek(&self, offset: usize) -> C {
+        return C::from_i64(self.pixels.get(offset).cloned().unwrap_or(0) as i64);
+    }
+
+    fn write(&mut self, offset: usize, value: C) -> Result<(), ErrorReason> {
+        let pixel = self.pixels.get_mut(offset).ok_or(ErrorReason::OutOfRange)?;
+        *pixel = match value.to_i64() {
+            Some(colour @ 0..=2) => colour as u8,
+            _ => return Err(ErrorReason::OutOfRange),
+        };
+        return Ok(());
+    }
+}
+
+/// One cell counting the instructions executed since it was mapped, or since
+/// the program last wrote to it to set the count.
+#[derive(Debug, Default, Clone)]
+pub struct TickTimer {
+    ticks: i64,
+}
+
+impl TickTimer {
+    pub fn new() -> TickTimer {
+        return TickTimer::default();
+    }
+
+    pub fn ticks(&self) -> i64 {
+        return self.ticks;
+    }
+}
+
+impl<C: Cell> Device<C> for TickTimer {
+    fn read(&mut self, offset: usize) -> Result<C, ErrorReason> {
+        return Ok(self.peek(offset));
+    }
+
+    fn peek(&self, _offset: usize) -> C {
+        return C::from_i64(self.ticks);
+    }
+
+    fn write(&mut self, _offset: usize, value: C) -> Result<(), ErrorReason> {
+        self.ticks = value.to_i64().ok_or(ErrorReason::OutOfRange)?;
+        return Ok(());
+    }
+
+    fn tick(&mut self) {
+        self.ticks = self.ticks.wrapping_add(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::compiled::CompiledComputer;
+    use super::super::computer::{IntCodeComputer, RunState};
+    use super::super::error::IntcodeError;
+    use super::super::host;
+
+    /// Reads the console until nothing is waiting, printing each character
+    /// in upper case.
+    const SHOUT: [i64; 11] = [1006,1001,10, 1001,1000,-32,1000, 1105,1,0, 99];
+
+    #[test]
+    fn test_console() {
+        let console = Arc::new(Mutex::new(Console::new()));
+        console.lock().unwrap().type_text("hello");
+
+        let mut computer = IntCodeComputer::new(&SHOUT, vec![]);
+        computer.map_device(1000..1000 + Console::SIZE, Box::new(console.clone()));
+
+        assert_eq!(computer.run(), Ok(RunState::Halted));
+        assert_eq!(console.lock().unwrap().printed(), "HELLO");
+        assert_eq!(computer.peek(1000), 0);
+    }
+
+    #[test]
+    fn test_compiled_backend_reaches_devices() {
+        let console = Arc::new(Mutex::new(Console::new()));
+        console.lock().unwrap().type_text("abc");
+
+        let mut computer = CompiledComputer::new(&SHOUT, vec![]);
+        computer.map_device(1000..1000 + Console::SIZE, Box::new(console.clone()));
+
+        assert_eq!(computer.run(), Ok(RunState::Halted));
+        assert_eq!(console.lock().unwrap().printed(), "ABC");
+    }
+
+    #[test]
+    fn test_framebuffer() {
+        let framebuffer = Arc::new(Mutex::new(Framebuffer::new(3, 2)));
+        // a white pixel at (0, 0), a black one at (1, 1), then a colour that does not exist
+        let intcode = [1101,1,0,100, 1101,0,0,104, 1101,3,0,105, 99];
+
+        let mut computer = IntCodeComputer::new(&intcode, vec![]);
+        computer.map_device(100..106, Box::new(framebuffer.clone()));
+
+        assert_eq!(computer.run(), Err(IntcodeError::new(8, 1101, ErrorReason::OutOfRange)));
+        assert_eq!(framebuffer.lock().unwrap().render(), "WTT\nT T\n");
+        assert_eq!(computer.peek(100), 1);
+    }
+
+    #[test]
+    fn test_tick_timer() {
+        let timer = Arc::new(Mutex::new(TickTimer::new()));
+        // outputs the timer after two instructions, then resets it to 10
+        let intcode = [1101,0,0,20, 1101,0,0,20, 4,50, 1101,10,0,50, 99];
+
+        let mut computer = IntCodeComputer::new(&intcode, vec![]);
+        computer.map_device(50..51, Box::new(timer.clone()));
+
+        assert_eq!(computer.run(), Ok(RunState::Halted));
+        assert_eq!(computer.outputs, vec![2]);
+        assert_eq!(timer.lock().unwrap().ticks(), 12);
+        assert_eq!(computer.peek(50), 12);
+    }
+
+    #[test]
+    fn test_host_calls_read_devices() {
+        let logged = Arc::new(Mutex::new(vec![]));
+        let sink = logged.clone();
+        // colours the first two pixels, logs the framebuffer as a string and outputs its length
+        let intcode = [1101,1,0,100, 1101,0,2,101, 1101,0,0,102, 1180,3,100,20, 4,20, 99];
+
+        let mut computer = IntCodeComputer::new(&intcode, vec![]);
+        computer.map_device(100..104, Box::new(Framebuffer::new(4, 1)));
+        computer.register_host_call(host::LOG, host::log(move |text| sink.lock().unwrap().push(text.to_owned())));
+
+        assert_eq!(computer.run(), Ok(RunState::Halted));
+        assert_eq!(*logged.lock().unwrap(), vec!["\u{1}\u{2}"]);
+        assert_eq!(computer.outputs, vec![2]);
+    }
+
+    #[test]
+    fn test_step_back_leaves_devices_alone() {
+        let timer = Arc::new(Mutex::new(TickTimer::new()));
+        let mut computer = IntCodeComputer::new(&[1101,10,0,50,99], vec![]);
+        computer.map_device(50..51, Box::new(timer.clone()));
+        computer.set_history(Some(10));
+        computer.run().unwrap();
+        let ticks = timer.lock().unwrap().ticks();
+
+        computer.step_back();
+        computer.step_back();
+
+        assert_eq!(computer.ptr(), 0);
+        assert_eq!(timer.lock().unwrap().ticks(), ticks);
+    }
+
+    #[test]
+    fn test_mapped_past_the_framebuffer() {
+        let framebuffer = Framebuffer::new(3, 2);
+        // writes a pixel at 150 and reads one at 106, both beyond the six pixels
+        let mut computer = IntCodeComputer::new(&[1101,1,0,150,99], vec![]);
+        computer.map_device(100..200, Box::new(framebuffer.clone()));
+        assert_eq!(computer.run(), Err(IntcodeError::new(0, 1101, ErrorReason::OutOfRange)));
+
+        let mut computer = IntCodeComputer::new(&[4,106,99], vec![]);
+        computer.map_device(100..200, Box::new(framebuffer));
+        assert_eq!(computer.run(), Err(IntcodeError::new(0, 4, ErrorReason::OutOfRange)));
+        assert_eq!(computer.peek(106), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one column")]
+    fn test_framebuffer_without_columns() {
+        Framebuffer::new(0, 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "overlaps")]
+    fn test_overlapping_devices() {
+        let mut computer = IntCodeComputer::new(&[99], vec![]);
+        computer.map_device(10..20, Box::new(Framebuffer::new(5, 2)));
+        computer.map_device(19..20, Box::new(TickTimer::new()));
+    }
+}
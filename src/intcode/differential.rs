@@ -0,0 +1,298 @@
+use std::collections::{BTreeSet, VecDeque};
+use std::fmt;
+
+use super::bigint::BigInt;
+use super::cell::{Cell, CheckedI64};
+use super::compiled::CompiledComputer;
+use super::computer::{IntCodeComputer, RunState};
+use super::error::IntcodeError;
+use super::memory::PagedMemory;
+
+/// One way of running Intcode, driven an instruction at a time. Values are
+/// compared as text so engines with different cell types can be compared.
+pub trait Engine {
+    fn name(&self) -> String;
+    fn step(&mut self) -> Result<RunState, IntcodeError>;
+    fn ptr(&self) -> usize;
+    fn relative_base(&self) -> i64;
+    fn peek(&self, address: usize) -> String;
+    fn output(&self, index: usize) -> Option<String>;
+    /// Addresses written by the last step.
+    fn written(&mut self) -> Vec<usize>;
+}
+
+struct Interpreted<C: Cell> {
+    name: &'static str,
+    machine: IntCodeComputer<VecDeque<C>, Vec<C>, PagedMemory<C>>,
+}
+
+impl<C: Cell> Interpreted<C> {
+    fn new(name: &'static str, intcode: &[i64], inputs: &[i64]) -> Interpreted<C> {
+        let cells: Vec<C> = intcode.iter().map(|value| C::from_i64(*value)).collect();
+        let inputs = inputs.iter().map(|value| C::from_i64(*value)).collect();
+        let mut machine = IntCodeComputer::with_cells(&cells, inputs);
+        machine.watch_writes();
+        return Interpreted { name, machine };
+    }
+}
+
+impl<C: Cell> Engine for Interpreted<C> {
+    fn name(&self) -> String {
+        return self.name.to_owned();
+    }
+
+    fn step(&mut self) -> Result<RunState, IntcodeError> {
+        return self.machine.next_operation();
+    }
+
+    fn ptr(&self) -> usize {
+        return self.machine.ptr();
+    }
+
+    fn relative_base(&self) -> i64 {
+        return self.machine.relative_base();
+    }
+
+    fn peek(&self, address: usize) -> String {
+        return self.machine.peek(address).to_string();
+    }
+
+    fn output(&self, index: usize) -> Option<String> {
+        return self.machine.outputs.get(index).map(|value| value.to_string());
+    }
+
+    fn written(&mut self) -> Vec<usize> {
+        let mut written = vec![];
+        while let Some(address) = self.machine.pop_written() {
+            written.push(address);
+        }
+        return written;
+    }
+}
+
+impl Engine for CompiledComputer {
+    fn name(&self) -> String {
+        return "compiled".to_owned();
+    }
+
+    fn step(&mut self) -> Result<RunState, IntcodeError> {
+        return self.next_operation();
+    }
+
+    fn ptr(&self) -> usize {
+        return IntCodeComputer::ptr(self);
+    }
+
+    fn relative_base(&self) -> i64 {
+        return IntCodeComputer::relative_base(self);
+    }
+
+    fn peek(&self, address: usize) -> String {
+        return IntCodeComputer::peek(self, address).to_string();
+    }
+
+    fn output(&self, index: usize) -> Option<String> {
+        return self.outputs.get(index).map(|value| value.to_string());
+    }
+
+    fn written(&mut self) -> Vec<usize> {
+        // left in place for the next step to drop overwritten translations
+        return IntCodeComputer::written(self).to_vec();
+    }
+}
+
+/// Every engine in the crate, loaded with the same program and inputs.
+pub fn engines(intcode: &[i64], inputs: &[i64]) -> Vec<Box<dyn Engine>> {
+    let mut uncached = Interpreted::<i64>::new("interpreter without decode cache", intcode, inputs);
+    uncached.machine.set_decode_cache(false);
+
+    return vec![
+        Box::new(Interpreted::<i64>::new("interpreter", intcode, inputs)),
+        Box::new(uncached),
+        Box::new(CompiledComputer::new(intcode, inputs.to_vec())),
+        Box::new(Interpreted::<i128>::new("i128 cells", intcode, inputs)),
+        Box::new(Interpreted::<CheckedI64>::new("checked cells", intcode, inputs)),
+        Box::new(Interpreted::<BigInt>::new("big cells", intcode, inputs)),
+    ];
+}
+
+/// How every engine finished, when they all agreed.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Agreement {
+    pub steps: u64,
+    /// `None` if the step limit was reached first.
+    pub result: Option<Result<RunState, IntcodeError>>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Field {
+    /// The result of the step: a run state or a fault.
+    Result,
+    Ptr,
+    RelativeBase,
+    Output(usize),
+    Memory(usize),
+}
+
+/// The first thing the engines disagreed on, with each engine's version of it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Divergence {
+    /// Steps completed, counting the one that diverged.
+    pub step: u64,
+    pub field: Field,
+    pub values: Vec<(String, String)>,
+}
+
+/// Steps every engine one instruction at a time, comparing the result, ptr,
+/// relative base, outputs and every address any of them wrote after each
+/// step. Stops when all halt, fault or wait for input together, or after
+/// `max_steps`.
+pub fn compare(engines: &mut [Box<dyn Engine>], max_steps: u64) -> Result<Agreement, Divergence> {
+    let mut outputs = 0;
+    for step in 1..=max_steps {
+        let results: Vec<Result<RunState, IntcodeError>> = engines.iter_mut().map(|engine| engine.step()).collect();
+        agree(engines, step, Field::Result, |index, _| format!("{:?}", results[index]))?;
+        agree(engines, step, Field::Ptr, |_, engine| engine.ptr().to_string())?;
+        agree(engines, step, Field::RelativeBase, |_, engine| engine.relative_base().to_string())?;
+        while engines.iter().any(|engine| engine.output(outputs).is_some()) {
+            agree(engines, step, Field::Output(outputs), |_, engine| format!("{:?}", engine.output(outputs)))?;
+            outputs += 1;
+        }
+        let written: BTreeSet<usize> = engines.iter_mut().flat_map(|engine| engine.written()).collect();
+        for address in written {
+            agree(engines, step, Field::Memory(address), |_, engine| engine.peek(address))?;
+        }
+
+        match &results[0] {
+            Ok(RunState::Running) | Ok(RunState::OutputReady) => (),
+            result => return Ok(Agreement { steps: step, result: Some(*result) }),
+        }
+    }
+
+    return Ok(Agreement { steps: max_steps, result: None });
+}
+
+fn agree<F: Fn(usize, &dyn Engine) -> String>(engines: &[Box<dyn Engine>], step: u64, field: Field, value: F) -> Result<(), Divergence> {
+    let values: Vec<(String, String)> = engines.iter().enumerate()
+        .map(|(index, engine)| (engine.name(), value(index, engine.as_ref())))
+        .collect();
+    if values.iter().all(|(_, value)| *value == values[0].1) {
+        return Ok(());
+    }
+    return Err(Divergence { step, field, values });
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            Field::Result => write!(f, "result"),
+            Field::Ptr => write!(f, "ptr"),
+            Field::RelativeBase => write!(f, "relative_base"),
+            Field::Output(index) => write!(f, "output {}", index),
+            Field::Memory(address) => write!(f, "memory at {}", address),
+        };
+    }
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Engines disagree on {} after {} steps:", self.field, self.step)?;
+        for (name, value) in &self.values {
+            writeln!(f, "  {:<34} {}", name, value)?;
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::error::ErrorReason;
+    use super::super::memory::Memory;
+
+    /// An interpreter that adds one to every value it writes.
+    struct OffByOne(Interpreted<i64>);
+
+    impl Engine for OffByOne {
+        fn name(&self) -> String {
+            return "off by one".to_owned();
+        }
+
+        fn step(&mut self) -> Result<RunState, IntcodeError> {
+            let result = self.0.step();
+            for address in self.0.machine.written().to_vec() {
+                let value = self.0.machine.peek(address);
+                self.0.machine.intcode.set(address, value + 1).unwrap();
+            }
+            return result;
+        }
+
+        fn ptr(&self) -> usize {
+            return self.0.ptr();
+        }
+
+        fn relative_base(&self) -> i64 {
+            return self.0.relative_base();
+        }
+
+        fn peek(&self, address: usize) -> String {
+            return self.0.peek(address);
+        }
+
+        fn output(&self, index: usize) -> Option<String> {
+            return self.0.output(index);
+        }
+
+        fn written(&mut self) -> Vec<usize> {
+            return self.0.written();
+        }
+    }
+
+    #[test]
+    fn test_engines_agree_on_puzzle_input() {
+        let intcode = super::super::parse(include_str!("../../resources/9.input"));
+        let mut engines = engines(&intcode, &[1]);
+
+        let agreement = compare(&mut engines, 10_000).unwrap();
+
+        assert_eq!(agreement.result, Some(Ok(RunState::Halted)));
+        assert_eq!(engines[5].output(0), Some("2204990589".to_owned()));
+    }
+
+    #[test]
+    fn test_overflow_diverges() {
+        // squares its input twice
+        let intcode = vec![3,13,2,13,13,13,2,13,13,13,4,13,99,0];
+        let mut engines = engines(&intcode, &[10_000_000_000]);
+
+        let divergence = compare(&mut engines, 100).unwrap_err();
+
+        assert_eq!((divergence.step, divergence.field), (2, Field::Result));
+        let overflow = IntcodeError::new(2, 2, ErrorReason::Overflow);
+        assert_eq!(divergence.values[4], ("checked cells".to_owned(), format!("{:?}", Err::<RunState, _>(overflow))));
+    }
+
+    #[test]
+    fn test_memory_divergence() {
+        let intcode = vec![1101,1,1,7,104,5,99,0];
+        let mut engines: Vec<Box<dyn Engine>> = vec![
+            Box::new(Interpreted::<i64>::new("interpreter", &intcode, &[])),
+            Box::new(OffByOne(Interpreted::new("interpreter", &intcode, &[]))),
+        ];
+
+        let divergence = compare(&mut engines, 100).unwrap_err();
+
+        assert_eq!(divergence.to_string(), "\
+Engines disagree on memory at 7 after 1 steps:
+  interpreter                        2
+  off by one                         3
+");
+    }
+
+    #[test]
+    fn test_step_limit() {
+        let mut engines = engines(&[1105,1,0], &[]);
+
+        assert_eq!(compare(&mut engines, 50), Ok(Agreement { steps: 50, result: None }));
+    }
+}
@@ -0,0 +1,199 @@
+use std::fmt;
+
+use super::computer::{operation_from_opcode, modes_from_opcode};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Operand {
+    Position(i64),
+    Immediate(i64),
+    Relative(i64),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Line {
+    Instruction { address: usize, mnemonic: &'static str, operands: Vec<Operand> },
+    Data { address: usize, value: i64 },
+}
+
+impl Line {
+    pub fn address(&self) -> usize {
+        return match self {
+            Line::Instruction { address, .. } => *address,
+            Line::Data { address, .. } => *address,
+        };
+    }
+
+    /// Number of memory cells this line occupies.
+    pub fn size(&self) -> usize {
+        return match self {
+            Line::Instruction { operands, .. } => operands.len() + 1,
+            Line::Data { .. } => 1,
+        };
+    }
+}
+
+/// Mnemonic and parameter count for an operation.
+pub fn instruction_info(operation: i64) -> Option<(&'static str, usize)> {
+    return match operation {
+        1 => Some(("ADD", 3)),
+        2 => Some(("MUL", 3)),
+        3 => Some(("IN", 1)),
+        4 => Some(("OUT", 1)),
+        5 => Some(("JT", 2)),
+        6 => Some(("JF", 2)),
+        7 => Some(("LT", 3)),
+        8 => Some(("EQ", 3)),
+        9 => Some(("ARB", 1)),
+        99 => Some(("HLT", 0)),
+        _ => None,
+    };
+}
+
+pub fn operation_from_mnemonic(mnemonic: &str) -> Option<i64> {
+    return [1, 2, 3, 4, 5, 6, 7, 8, 9, 99].iter()
+        .cloned()
+        .find(|operation| instruction_info(*operation).unwrap().0.eq_ignore_ascii_case(mnemonic));
+}
+
+/// Decodes the instruction at `address`, or `None` if the word there is not a
+/// valid instruction (unknown operation, bad mode, or operands past the end).
+pub fn decode(intcode: &[i64], address: usize) -> Option<Line> {
+    return decode_with(intcode, address, instruction_info);
+}
+
+/// Like `decode`, for an instruction set described by `info`, such as
+/// `OpcodeTable::info`.
+pub fn decode_with<F: Fn(i64) -> Option<(&'static str, usize)>>(intcode: &[i64], address: usize, info: F) -> Option<Line> {
+    let opcode = *intcode.get(address)?;
+    if opcode < 0 {
+        return None;
+    }
+
+    let operation = operation_from_opcode(opcode);
+    let (mnemonic, parameters) = info(operation)?;
+    let modes = modes_from_opcode(opcode, parameters);
+    if encode(operation, &modes) != opcode || address + parameters >= intcode.len() {
+        return None;
+    }
+
+    let mut operands = vec![];
+    for (index, mode) in modes.iter().enumerate() {
+        let value = intcode[address + index + 1];
+        operands.push(match mode {
+            0 => Operand::Position(value),
+            1 => Operand::Immediate(value),
+            2 => Operand::Relative(value),
+            _ => return None,
+        });
+    }
+
+    return Some(Line::Instruction { address, mnemonic, operands });
+}
+
+/// Builds an opcode from an operation and its parameter modes.
+pub fn encode(operation: i64, modes: &[i64]) -> i64 {
+    let mut opcode = operation;
+    let mut base = 100;
+    for mode in modes {
+        opcode += mode * base;
+        base *= 10;
+    }
+    return opcode;
+}
+
+/// Linear sweep from address 0. Anything that does not decode is data.
+pub fn disassemble(intcode: &[i64]) -> Vec<Line> {
+    let mut lines = vec![];
+    let mut address = 0;
+    while address < intcode.len() {
+        let line = match decode(intcode, address) {
+            Some(line) => line,
+            None => Line::Data { address, value: intcode[address] },
+        };
+        address += line.size();
+        lines.push(line);
+    }
+
+    return lines;
+}
+
+pub fn listing(intcode: &[i64]) -> String {
+    return disassemble(intcode).iter()
+        .map(|line| format!("{}\n", line))
+        .collect();
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            Operand::Position(value) => write!(f, "{}", value),
+            Operand::Immediate(value) => write!(f, "#{}", value),
+            Operand::Relative(value) => write!(f, "@{}", value),
+        };
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            Line::Instruction { address, mnemonic, operands } => {
+                if operands.is_empty() {
+                    return write!(f, "{:>5}: {}", address, mnemonic);
+                }
+                let operands: Vec<String> = operands.iter().map(|operand| operand.to_string()).collect();
+                write!(f, "{:>5}: {:<5} {}", address, mnemonic, operands.join(", "))
+            },
+            Line::Data { address, value } => write!(f, "{:>5}: {:<5} {}", address, ".data", value),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_modes() {
+        let intcode = vec![21101,4,-3,7];
+        let expected = Line::Instruction {
+            address: 0,
+            mnemonic: "ADD",
+            operands: vec![Operand::Immediate(4), Operand::Immediate(-3), Operand::Relative(7)],
+        };
+
+        assert_eq!(decode(&intcode, 0), Some(expected));
+    }
+
+    #[test]
+    fn test_listing() {
+        let intcode = vec![109,1,204,-1,1001,100,1,100,99,12345,-7];
+        let expected = "    0: ARB   #1\n    \
+                            2: OUT   @-1\n    \
+                            4: ADD   100, #1, 100\n    \
+                            8: HLT\n    \
+                            9: .data 12345\n   \
+                           10: .data -7\n";
+
+        assert_eq!(listing(&intcode), expected);
+    }
+
+    #[test]
+    fn test_unknown_words_are_data() {
+        // bad operation, bad mode, extra mode digits and a truncated instruction
+        let intcode = vec![42,301,10099,1101,1];
+
+        let lines = disassemble(&intcode);
+
+        assert_eq!(lines.len(), 5);
+        for (address, line) in lines.iter().enumerate() {
+            assert_eq!(*line, Line::Data { address, value: intcode[address] });
+        }
+    }
+
+    #[test]
+    fn test_operation_from_mnemonic() {
+        assert_eq!(operation_from_mnemonic("arb"), Some(9));
+        assert_eq!(operation_from_mnemonic("HLT"), Some(99));
+        assert_eq!(operation_from_mnemonic("NOP"), None);
+    }
+}
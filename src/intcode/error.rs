@@ -0,0 +1,66 @@
+use std::error::Error;
+use std::fmt;
+
+use super::protection::ViolationKind;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ErrorReason {
+    UnknownOpcode,
+    UnknownParameterMode(i64),
+    NegativeAddress(i64),
+    /// A write needed more memory than the configured ceiling of this many cells.
+    MemoryLimitExceeded(usize),
+    /// A sum or product did not fit in the machine's cell type, or a relative
+    /// address or base did not fit in an `i64`.
+    Overflow,
+    /// A value used as an address, jump target or relative base offset did not fit in an `i64`.
+    OutOfRange,
+    /// The program broke the code map given to `set_protection`.
+    Protection(ViolationKind),
+    /// A registered instruction with no write parameter returned `Effect::Write`.
+    NoWriteParameter,
+    /// A host call was made to a service number with nothing registered.
+    UnknownHostCall(i64),
+}
+
+/// A fault raised by a running program, recording the instruction that caused it.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct IntcodeError {
+    pub ptr: usize,
+    pub opcode: i64,
+    pub reason: ErrorReason,
+}
+
+impl IntcodeError {
+    pub fn new(ptr: usize, opcode: i64, reason: ErrorReason) -> IntcodeError {
+        return IntcodeError {
+            ptr,
+            opcode,
+            reason,
+        };
+    }
+}
+
+impl fmt::Display for ErrorReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            ErrorReason::UnknownOpcode => write!(f, "unknown opcode"),
+            ErrorReason::UnknownParameterMode(mode) => write!(f, "unknown parameter mode {}", mode),
+            ErrorReason::NegativeAddress(address) => write!(f, "negative address {}", address),
+            ErrorReason::MemoryLimitExceeded(limit) => write!(f, "memory limit of {} cells exceeded", limit),
+            ErrorReason::Overflow => write!(f, "arithmetic overflow"),
+            ErrorReason::OutOfRange => write!(f, "value out of range for an address"),
+            ErrorReason::Protection(kind) => write!(f, "{}", kind),
+            ErrorReason::NoWriteParameter => write!(f, "write by an instruction with no write parameter"),
+            ErrorReason::UnknownHostCall(service) => write!(f, "unknown host call {}", service),
+        };
+    }
+}
+
+impl fmt::Display for IntcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{} at ptr {} (opcode {})", self.reason, self.ptr, self.opcode);
+    }
+}
+
+impl Error for IntcodeError {}
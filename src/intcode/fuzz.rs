@@ -0,0 +1,380 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+use super::budget::Budget;
+use super::computer::{IntCodeComputer, RunState};
+use super::decode::Instruction;
+use super::disassembler::{self, encode, instruction_info, Line, Operand};
+use super::error::{ErrorReason, IntcodeError};
+use super::parse;
+use super::rng::Rng;
+
+/// Operations the generator picks from, with their parameter counts.
+const OPERATIONS: [(i64, usize); 9] = [(1, 3), (2, 3), (3, 1), (4, 1), (5, 2), (6, 2), (7, 3), (8, 3), (9, 1)];
+
+/// Values the machine has to survive in any operand or input.
+const EXTREMES: [i64; 5] = [i64::MAX, i64::MIN, i64::MAX / 2, i64::MIN / 2, 1 << 40];
+
+/// A program and the inputs queued for it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Case {
+    pub intcode: Vec<i64>,
+    pub inputs: Vec<i64>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Invariant {
+    /// Running never panics, whatever the program does.
+    NoPanic,
+    /// Running again, or stopping halfway and resuming from a snapshot,
+    /// ends in exactly the same state.
+    DeterministicReplay,
+    /// Wherever the disassembler sees an instruction the machine decodes the
+    /// same operation and modes, and the machine only rejects an opcode the
+    /// disassembler rejects too.
+    DecodeMatchesDisassembly,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Failure {
+    pub invariant: Invariant,
+    pub message: String,
+}
+
+/// Generates random, well-formed programs: valid instructions with operands
+/// that mostly point back into the program, ending in HLT. What they do to
+/// themselves once running is up to chance.
+pub struct Fuzzer {
+    rng: Rng,
+    /// Most instructions in a generated program.
+    pub size: usize,
+}
+
+impl Fuzzer {
+    pub fn new(seed: u64) -> Fuzzer {
+        return Fuzzer { rng: Rng::new(seed), size: 12 };
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        return self.rng.below(bound as u64) as usize;
+    }
+
+    fn value(&mut self, length: usize) -> i64 {
+        return match self.below(8) {
+            0..=3 => self.below(length) as i64,
+            4..=6 => self.below(11) as i64 - 5,
+            _ => EXTREMES[self.below(EXTREMES.len())],
+        };
+    }
+
+    pub fn case(&mut self) -> Case {
+        let instructions = 1 + self.below(self.size);
+        let length = instructions * 4 + 1;
+        let mut intcode = vec![];
+        for _ in 0..instructions {
+            let (operation, parameters) = OPERATIONS[self.below(OPERATIONS.len())];
+            let writes = match operation {
+                3 => Some(0),
+                1 | 2 | 7 | 8 => Some(2),
+                _ => None,
+            };
+            let modes: Vec<i64> = (0..parameters)
+                .map(|index| if writes == Some(index) { [0, 2][self.below(2)] } else { self.below(3) as i64 })
+                .collect();
+            intcode.push(encode(operation, &modes));
+            for _ in 0..parameters {
+                let value = self.value(length);
+                intcode.push(value);
+            }
+        }
+        intcode.push(99);
+
+        let inputs = (0..self.below(4)).map(|_| self.value(length)).collect();
+        return Case { intcode, inputs };
+    }
+}
+
+/// Checks every invariant for a case, running at most `steps` instructions.
+pub fn check(case: &Case, steps: u64) -> Result<(), Failure> {
+    return match panic::catch_unwind(AssertUnwindSafe(|| check_replay(case, steps))) {
+        Ok(result) => result,
+        Err(payload) => {
+            let message = match (payload.downcast_ref::<&str>(), payload.downcast_ref::<String>()) {
+                (Some(message), _) => message.to_string(),
+                (_, Some(message)) => message.clone(),
+                _ => "panicked".to_owned(),
+            };
+            Err(Failure { invariant: Invariant::NoPanic, message })
+        },
+    };
+}
+
+/// Where a run ended up, for comparing runs.
+#[derive(Debug, PartialEq)]
+struct Ending {
+    result: Result<RunState, IntcodeError>,
+    outputs: Vec<i64>,
+    snapshot: String,
+}
+
+fn ending(computer: &IntCodeComputer, result: Result<RunState, IntcodeError>, outputs: Vec<i64>) -> Ending {
+    return Ending { result, outputs, snapshot: computer.snapshot().to_text() };
+}
+
+fn check_replay(case: &Case, steps: u64) -> Result<(), Failure> {
+    let stepped = check_decoding(case, steps)?;
+
+    let mut computer = IntCodeComputer::new(&case.intcode, case.inputs.clone());
+    computer.set_budget(Budget::steps(steps));
+    let result = computer.run();
+    let run = ending(&computer, result, computer.outputs.clone());
+
+    let mut first = IntCodeComputer::new(&case.intcode, case.inputs.clone());
+    first.set_budget(Budget::steps(steps / 2));
+    let mut result = first.run();
+    let mut outputs = first.outputs.clone();
+    let mut last = first;
+    if result == Ok(RunState::BudgetExhausted) {
+        let mut second = IntCodeComputer::new(&[], last.inputs.iter().cloned().collect());
+        second.restore(&last.snapshot());
+        second.set_budget(Budget::steps(steps - last.executed()));
+        result = second.run();
+        outputs.extend(second.outputs.iter());
+        last = second;
+    }
+    let resumed = ending(&last, result, outputs);
+
+    for (name, other) in &[("running", &run), ("resuming from a snapshot", &resumed)] {
+        if **other != stepped {
+            let message = format!("stepping ended with {:?}, {} with {:?}", stepped, name, other);
+            return Err(Failure { invariant: Invariant::DeterministicReplay, message });
+        }
+    }
+    return Ok(());
+}
+
+/// Steps through a case comparing each instruction with the disassembler.
+fn check_decoding(case: &Case, steps: u64) -> Result<Ending, Failure> {
+    let mut computer = IntCodeComputer::new(&case.intcode, case.inputs.clone());
+    let result = loop {
+        if computer.executed() >= steps {
+            break Ok(RunState::BudgetExhausted);
+        }
+        let ptr = computer.ptr();
+        let memory = computer.intcode.segments()[0].1.to_vec();
+        let line = disassembler::decode(&memory, ptr);
+        if let Some(line) = &line {
+            agree(line, Instruction::decode(memory[ptr]))?;
+        }
+
+        match computer.next_operation() {
+            Ok(RunState::Running) | Ok(RunState::OutputReady) => continue,
+            Err(IntcodeError { reason: ErrorReason::UnknownOpcode, .. })
+            | Err(IntcodeError { reason: ErrorReason::UnknownParameterMode(_), .. }) if line.is_some() => {
+                let message = format!("the machine rejected {}", line.unwrap());
+                return Err(Failure { invariant: Invariant::DecodeMatchesDisassembly, message });
+            },
+            result => break result,
+        }
+    };
+    return Ok(ending(&computer, result, computer.outputs.clone()));
+}
+
+fn agree(line: &Line, instruction: Instruction) -> Result<(), Failure> {
+    if let Line::Instruction { mnemonic, operands, .. } = line {
+        let decoded = instruction_info(instruction.operation).map(|(mnemonic, _)| mnemonic);
+        let modes: Vec<i64> = operands.iter()
+            .map(|operand| match operand {
+                Operand::Position(_) => 0,
+                Operand::Immediate(_) => 1,
+                Operand::Relative(_) => 2,
+            })
+            .collect();
+        if decoded != Some(*mnemonic) || modes[..] != instruction.modes[..modes.len()] {
+            let message = format!("'{}' decodes as {:?}", line, instruction);
+            return Err(Failure { invariant: Invariant::DecodeMatchesDisassembly, message });
+        }
+    }
+    return Ok(());
+}
+
+/// Shrinks a failing case to one that still breaks the same invariant, by
+/// dropping inputs and runs of cells and zeroing cells until nothing more helps.
+pub fn minimise(case: &Case, failure: &Failure, steps: u64) -> Case {
+    return minimise_with(case, |candidate| match check(candidate, steps) {
+        Err(other) => other.invariant == failure.invariant,
+        Ok(()) => false,
+    });
+}
+
+fn minimise_with<F: Fn(&Case) -> bool>(case: &Case, fails: F) -> Case {
+    let mut case = case.clone();
+    let mut shrunk = true;
+    while shrunk {
+        shrunk = false;
+        for index in (0..case.inputs.len()).rev() {
+            let mut candidate = case.clone();
+            candidate.inputs.remove(index);
+            if fails(&candidate) {
+                case = candidate;
+                shrunk = true;
+            }
+        }
+        for run in &[4, 2, 1] {
+            let mut start = 0;
+            while start + run <= case.intcode.len() && case.intcode.len() > *run {
+                let mut candidate = case.clone();
+                candidate.intcode.drain(start..start + run);
+                if fails(&candidate) {
+                    case = candidate;
+                    shrunk = true;
+                } else {
+                    start += 1;
+                }
+            }
+        }
+        for index in 0..case.intcode.len() {
+            if case.intcode[index] != 0 {
+                let mut candidate = case.clone();
+                candidate.intcode[index] = 0;
+                if fails(&candidate) {
+                    case = candidate;
+                    shrunk = true;
+                }
+            }
+        }
+    }
+    return case;
+}
+
+/// Checks `cases` generated cases, returning each failure minimised.
+pub fn fuzz(seed: u64, cases: usize, steps: u64) -> Vec<(Case, Failure)> {
+    let mut fuzzer = Fuzzer::new(seed);
+    let mut failures = vec![];
+    for _ in 0..cases {
+        let case = fuzzer.case();
+        if let Err(failure) = check(&case, steps) {
+            let minimised = minimise(&case, &failure, steps);
+            let failure = check(&minimised, steps).unwrap_err();
+            failures.push((minimised, failure));
+        }
+    }
+    return failures;
+}
+
+impl Case {
+    /// The program on the first line as a puzzle input would have it, and the
+    /// inputs, also comma separated, on the second.
+    pub fn to_text(&self) -> String {
+        return format!("{}\n{}\n", join(&self.intcode), join(&self.inputs));
+    }
+
+    pub fn from_text(text: &str) -> Case {
+        let mut lines = text.lines();
+        let intcode = parse(lines.next().unwrap_or(""));
+        let inputs = match lines.next() {
+            Some(line) if !line.trim().is_empty() => parse(line),
+            _ => vec![],
+        };
+        return Case { intcode, inputs };
+    }
+
+    /// Writes the case into `directory`, named after its contents so the
+    /// same case is only ever saved once, and returns the path.
+    pub fn save(&self, directory: &Path) -> io::Result<PathBuf> {
+        let text = self.to_text();
+        let hash = text.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100_0000_01b3));
+        fs::create_dir_all(directory)?;
+        let path = directory.join(format!("{:016x}.input", hash));
+        fs::write(&path, text)?;
+        return Ok(path);
+    }
+}
+
+fn join(values: &[i64]) -> String {
+    return values.iter().map(|value| value.to_string()).collect::<Vec<String>>().join(",");
+}
+
+impl fmt::Display for Invariant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            Invariant::NoPanic => write!(f, "no panics"),
+            Invariant::DeterministicReplay => write!(f, "deterministic replay"),
+            Invariant::DecodeMatchesDisassembly => write!(f, "decode matches disassembly"),
+        };
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{} broken: {}", self.invariant, self.message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEPS: u64 = 1_000;
+
+    #[test]
+    fn test_generated_programs_are_well_formed() {
+        let mut fuzzer = Fuzzer::new(7);
+        for _ in 0..100 {
+            let case = fuzzer.case();
+            let lines = disassembler::disassemble(&case.intcode);
+
+            assert!(lines.iter().all(|line| matches!(line, Line::Instruction { .. })), "{:?}", case);
+            let halt = Line::Instruction { address: case.intcode.len() - 1, mnemonic: "HLT", operands: vec![] };
+            assert_eq!(lines.last(), Some(&halt));
+        }
+    }
+
+    #[test]
+    fn test_fuzz() {
+        let failures = fuzz(2019, 300, STEPS);
+
+        assert_eq!(failures, vec![]);
+    }
+
+    #[test]
+    fn test_minimise() {
+        let case = Case { intcode: vec![1101,2,3,0,104,7,4,0,99], inputs: vec![1, 2] };
+        // pretend any output of 5 is a bug
+        let fails = |case: &Case| {
+            let mut computer = IntCodeComputer::new(&case.intcode, case.inputs.clone());
+            computer.set_budget(Budget::steps(STEPS));
+            let _ = computer.run();
+            return computer.outputs.contains(&5);
+        };
+        assert!(fails(&case));
+
+        let minimised = minimise_with(&case, fails);
+
+        assert_eq!(minimised, Case { intcode: vec![1101,2,3,0,4], inputs: vec![] });
+    }
+
+    /// Every case the fuzzer has ever saved keeps passing.
+    #[test]
+    fn test_regressions() {
+        let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/fuzz");
+        for entry in fs::read_dir(directory).unwrap() {
+            let path = entry.unwrap().path();
+            let case = Case::from_text(&fs::read_to_string(&path).unwrap());
+
+            assert_eq!(check(&case, STEPS), Ok(()), "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn test_text_round_trip() {
+        let case = Case { intcode: vec![3,0,4,0,99], inputs: vec![-1, i64::MAX] };
+
+        assert_eq!(case.to_text(), "3,0,4,0,99\n-1,9223372036854775807\n");
+        assert_eq!(Case::from_text(&case.to_text()), case);
+        assert_eq!(Case::from_text("99\n"), Case { intcode: vec![99], inputs: vec![] });
+    }
+}
@@ -0,0 +1,82 @@
+use std::collections::VecDeque;
+
+/// Everything needed to undo one executed instruction.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct UndoRecord<C = i64> {
+    pub ptr: usize,
+    pub relative_base: i64,
+    /// Addresses written, with the values they held before, in write order.
+    pub writes: Vec<(usize, C)>,
+    /// The value taken from the input, which the machine cannot put back itself.
+    pub input: Option<C>,
+    /// The value sent to the output, which the machine cannot take back itself.
+    pub output: Option<C>,
+}
+
+impl<C> UndoRecord<C> {
+    pub(crate) fn new(ptr: usize, relative_base: i64) -> UndoRecord<C> {
+        return UndoRecord { ptr, relative_base, writes: vec![], input: None, output: None };
+    }
+
+    pub fn wrote(&self, address: usize) -> bool {
+        return self.writes.iter().any(|(written, _)| *written == address);
+    }
+}
+
+/// The most recent `limit` undo records, newest last.
+#[derive(Debug, Clone)]
+pub(crate) struct History<C = i64> {
+    records: VecDeque<UndoRecord<C>>,
+    limit: usize,
+}
+
+impl<C> History<C> {
+    pub(crate) fn new(limit: usize) -> History<C> {
+        return History { records: VecDeque::new(), limit };
+    }
+
+    pub(crate) fn push(&mut self, record: UndoRecord<C>) {
+        if self.limit == 0 {
+            return;
+        }
+        if self.records.len() == self.limit {
+            self.records.pop_front();
+        }
+        self.records.push_back(record);
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<UndoRecord<C>> {
+        return self.records.pop_back();
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        return self.records.len();
+    }
+
+    /// Records to pop to undo the most recent write to `address`.
+    pub(crate) fn steps_since_write(&self, address: usize) -> Option<usize> {
+        return self.records.iter().rev().position(|record| record.wrote(address)).map(|index| index + 1);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.records.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_drops_oldest() {
+        let mut history: History = History::new(2);
+        for ptr in 0..3 {
+            history.push(UndoRecord::new(ptr, 0));
+        }
+
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.pop().map(|record| record.ptr), Some(2));
+        assert_eq!(history.pop().map(|record| record.ptr), Some(1));
+        assert_eq!(history.pop(), None);
+    }
+}
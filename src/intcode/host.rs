@@ -0,0 +1,247 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::cell::Cell;
+use super::computer::IntCodeComputer;
+use super::error::ErrorReason;
+use super::io::{InputSource, OutputSink};
+use super::memory::Memory;
+use super::rng::Rng;
+
+/// The operation of `SYS service, argument, result`, which calls the host
+/// service registered under `service` with `argument` and stores what it
+/// returns in `result`.
+pub const SYSCALL: i64 = 80;
+
+/// Milliseconds since the Unix epoch. The argument is ignored.
+pub const CLOCK: i64 = 1;
+/// A number from 0 up to, but not including, the argument.
+pub const RANDOM: i64 = 2;
+/// Logs the string at the argument's address and returns its length.
+pub const LOG: i64 = 3;
+/// The argument is the address of three cells: the address of a file name,
+/// the address of a buffer and the buffer's size. Copies as much of the file
+/// as fits into the buffer and returns the file's length, or -1 if it cannot
+/// be read.
+pub const READ_FILE: i64 = 4;
+
+/// Strings end at the first 0, or after this many characters.
+const MAX_STRING: usize = 4096;
+
+/// A host service. Given the machine's memory and the argument, it returns
+/// the value to store in the result, or faults the machine.
+pub type HostCall<C> = Box<dyn for<'a> FnMut(&mut HostContext<'a, C>, C) -> Result<C, ErrorReason> + Send>;
+
+/// What a host call sees of the machine. Reads see mapped devices the way a
+/// debugger does, without their side effects. Writes are held back and made
+/// by the machine once the call returns, so they are traced, recorded,
+/// checked and sent to devices like any other write.
+pub struct HostContext<'a, C> {
+    memory: &'a dyn Fn(usize) -> C,
+    writes: Vec<(usize, C)>,
+}
+
+impl<'a, C: Cell> HostContext<'a, C> {
+    pub(crate) fn new(memory: &'a dyn Fn(usize) -> C) -> HostContext<'a, C> {
+        return HostContext { memory, writes: vec![] };
+    }
+
+    pub(crate) fn into_writes(self) -> Vec<(usize, C)> {
+        return self.writes;
+    }
+
+    /// Reads memory, including what this call has written so far.
+    pub fn peek(&self, address: usize) -> C {
+        return match self.writes.iter().rev().find(|(written, _)| *written == address) {
+            Some((_, value)) => value.clone(),
+            None => (self.memory)(address),
+        };
+    }
+
+    pub fn poke(&mut self, address: usize, value: C) {
+        self.writes.push((address, value));
+    }
+
+    /// The zero-terminated string of character codes at `address`.
+    pub fn read_string(&self, address: usize) -> Result<String, ErrorReason> {
+        let mut text = String::new();
+        for offset in 0..MAX_STRING {
+            let code = self.peek(address + offset).to_i64().ok_or(ErrorReason::OutOfRange)?;
+            match code {
+                0 => return Ok(text),
+                1..=255 => text.push(code as u8 as char),
+                _ => return Err(ErrorReason::OutOfRange),
+            }
+        }
+        return Ok(text);
+    }
+}
+
+/// Reads a value passed as an address.
+pub fn address<C: Cell>(value: &C) -> Result<usize, ErrorReason> {
+    return match value.to_i64() {
+        Some(address) if address < 0 => Err(ErrorReason::NegativeAddress(address)),
+        Some(address) => Ok(address as usize),
+        None => Err(ErrorReason::OutOfRange),
+    };
+}
+
+pub fn clock<C: Cell>() -> HostCall<C> {
+    return Box::new(|_, _| {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        return Ok(C::from_i64(now.as_millis() as i64));
+    });
+}
+
+/// Random numbers from a seed, so a run can be repeated.
+pub fn random<C: Cell>(seed: u64) -> HostCall<C> {
+    let mut rng = Rng::new(seed);
+    return Box::new(move |_, bound| match bound.to_i64() {
+        Some(bound) if bound > 0 => Ok(C::from_i64(rng.below(bound as u64) as i64)),
+        _ => Err(ErrorReason::OutOfRange),
+    });
+}
+
+/// Hands each logged string to `sink`.
+pub fn log<C: Cell, F: FnMut(&str) + Send + 'static>(mut sink: F) -> HostCall<C> {
+    return Box::new(move |context, argument| {
+        let text = context.read_string(address(&argument)?)?;
+        sink(&text);
+        return Ok(C::from_i64(text.len() as i64));
+    });
+}
+
+/// Reads files from `directory` only. Names may not contain path
+/// separators or start with a dot, so nothing outside it can be reached.
+pub fn read_file<C: Cell>(directory: PathBuf) -> HostCall<C> {
+    return Box::new(move |context, argument| {
+        let block = address(&argument)?;
+        let name = context.read_string(address(&context.peek(block))?)?;
+        let buffer = address(&context.peek(block + 1))?;
+        let size = address(&context.peek(block + 2))?;
+
+        let safe = !name.is_empty() && !name.starts_with('.') && !name.contains(['/', '\\']);
+        if !safe {
+            return Ok(C::from_i64(-1));
+        }
+        let (length, contents) = match read_prefix(&directory.join(&name), size) {
+            Ok(read) => read,
+            Err(_) => return Ok(C::from_i64(-1)),
+        };
+        for (offset, byte) in contents.iter().enumerate() {
+            context.poke(buffer + offset, C::from_i64(*byte as i64));
+        }
+        return Ok(C::from_i64(length as i64));
+    });
+}
+
+/// The length of a file and at most its first `size` bytes.
+fn read_prefix(path: &Path, size: usize) -> io::Result<(u64, Vec<u8>)> {
+    let file = File::open(path)?;
+    let length = file.metadata()?.len();
+    let mut contents = vec![];
+    file.take(size as u64).read_to_end(&mut contents)?;
+    return Ok((length, contents));
+}
+
+/// Registers the clock, random numbers seeded with `seed`, logging to
+/// stderr, and reading files from `directory`, under their usual numbers.
+pub fn register_standard<I, O, M>(computer: &mut IntCodeComputer<I, O, M>, directory: PathBuf, seed: u64)
+    where I: InputSource<M::Cell>, O: OutputSink<M::Cell>, M: Memory {
+    computer.register_host_call(CLOCK, clock());
+    computer.register_host_call(RANDOM, random(seed));
+    computer.register_host_call(LOG, log(|text| eprintln!("{}", text)));
+    computer.register_host_call(READ_FILE, read_file(directory));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::sync::{Arc, Mutex};
+    use super::super::computer::RunState;
+    use super::super::error::IntcodeError;
+
+    fn resources() -> PathBuf {
+        return Path::new(env!("CARGO_MANIFEST_DIR")).join("resources");
+    }
+
+    fn ascii(text: &str) -> Vec<i64> {
+        return text.bytes().map(|byte| byte as i64).chain(Some(0)).collect();
+    }
+
+    #[test]
+    fn test_log_and_random() {
+        let logged = Arc::new(Mutex::new(vec![]));
+        let sink = logged.clone();
+        // SYS LOG 13 -> 0, OUT 0, SYS RANDOM 10 -> 0, OUT 0, HLT, "hello"
+        let mut intcode = vec![1180,3,13,0, 4,0, 1180,2,10,0, 4,0, 99];
+        intcode.extend(ascii("hello"));
+
+        let mut computer = IntCodeComputer::new(&intcode, vec![]);
+        computer.register_host_call(LOG, log(move |text| sink.lock().unwrap().push(text.to_owned())));
+        computer.register_host_call(RANDOM, random(1));
+
+        assert_eq!(computer.run(), Ok(RunState::Halted));
+        assert_eq!(*logged.lock().unwrap(), vec!["hello"]);
+        assert_eq!(computer.outputs[0], 5);
+        assert!((0..10).contains(&computer.outputs[1]));
+    }
+
+    #[test]
+    fn test_read_file() {
+        // SYS READ_FILE 6 -> 5, HLT; the block at 6, the name at 9, the buffer at 22
+        let mut intcode = vec![1180,4,6,5, 99, 0, 9,22,4];
+        intcode.extend(ascii("2_test.input"));
+
+        let mut computer = IntCodeComputer::new(&intcode, vec![]);
+        computer.register_host_call(READ_FILE, read_file(resources()));
+        computer.run().unwrap();
+
+        let expected = fs::read(resources().join("2_test.input")).unwrap();
+        assert_eq!(computer.peek(5), expected.len() as i64);
+        let read: Vec<u8> = (22..26).map(|address| computer.peek(address) as u8).collect();
+        assert_eq!(read, expected[..4].to_vec());
+        assert_eq!(computer.peek(26), 0);
+    }
+
+    #[test]
+    fn test_read_file_stays_in_directory() {
+        let mut intcode = vec![1180,4,6,5, 99, 0, 9,30,10];
+        intcode.extend(ascii("../Cargo.toml"));
+
+        let mut computer = IntCodeComputer::new(&intcode, vec![]);
+        computer.register_host_call(READ_FILE, read_file(resources()));
+        computer.run().unwrap();
+
+        assert_eq!(computer.peek(5), -1);
+        assert_eq!(computer.peek(30), 0);
+    }
+
+    #[test]
+    fn test_unknown_host_call() {
+        let mut computer = IntCodeComputer::new(&[1180,7,0,0,99], vec![]);
+        computer.register_host_call(CLOCK, clock());
+
+        assert_eq!(computer.run(), Err(IntcodeError::new(0, 1180, ErrorReason::UnknownHostCall(7))));
+        assert_eq!(computer.opcodes().info(SYSCALL), Some(("SYS", 3)));
+    }
+
+    #[test]
+    fn test_host_writes_are_undone() {
+        let mut intcode = vec![1180,4,6,5, 99, 0, 9,22,4];
+        intcode.extend(ascii("2_test.input"));
+
+        let mut computer = IntCodeComputer::new(&intcode, vec![]);
+        computer.register_host_call(READ_FILE, read_file(resources()));
+        computer.set_history(Some(10));
+        computer.run().unwrap();
+        assert_ne!(computer.peek(22), 0);
+        computer.step_back();
+        computer.step_back();
+
+        assert_eq!((computer.ptr(), computer.peek(5), computer.peek(22)), (0, 0, 0));
+    }
+}
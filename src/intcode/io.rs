@@ -0,0 +1,158 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::io::Write;
+use std::sync::mpsc::{Receiver, Sender};
+
+/// Supplies values to the input instruction. Returning `None` pauses the
+/// machine with `RunState::AwaitingInput` until a value is available.
+pub trait InputSource<C = i64> {
+    fn read(&mut self) -> Option<C>;
+}
+
+/// Receives every value produced by the output instruction.
+pub trait OutputSink<C = i64> {
+    fn write(&mut self, value: C);
+}
+
+impl<C> InputSource<C> for VecDeque<C> {
+    fn read(&mut self) -> Option<C> {
+        return self.pop_front();
+    }
+}
+
+impl<C> OutputSink<C> for Vec<C> {
+    fn write(&mut self, value: C) {
+        self.push(value);
+    }
+}
+
+impl<C> OutputSink<C> for VecDeque<C> {
+    fn write(&mut self, value: C) {
+        self.push_back(value);
+    }
+}
+
+pub struct FnInput<F>(pub F);
+
+impl<C, F: FnMut() -> Option<C>> InputSource<C> for FnInput<F> {
+    fn read(&mut self) -> Option<C> {
+        return (self.0)();
+    }
+}
+
+pub struct FnOutput<F>(pub F);
+
+impl<C, F: FnMut(C)> OutputSink<C> for FnOutput<F> {
+    fn write(&mut self, value: C) {
+        (self.0)(value);
+    }
+}
+
+/// Reads one number per line from stdin. End of input pauses the machine.
+pub struct StdinInput {
+    prompt: Option<String>,
+}
+
+impl StdinInput {
+    pub fn new() -> StdinInput {
+        return StdinInput { prompt: None };
+    }
+
+    pub fn with_prompt(prompt: &str) -> StdinInput {
+        return StdinInput { prompt: Some(prompt.to_owned()) };
+    }
+}
+
+impl Default for StdinInput {
+    fn default() -> StdinInput {
+        return StdinInput::new();
+    }
+}
+
+impl InputSource for StdinInput {
+    fn read(&mut self) -> Option<i64> {
+        loop {
+            if let Some(prompt) = &self.prompt {
+                print!("{}", prompt);
+                io::stdout().flush().ok()?;
+            }
+
+            let mut line = String::new();
+            match io::stdin().read_line(&mut line) {
+                Ok(0) | Err(_) => return None,
+                Ok(_) => (),
+            }
+
+            match line.trim().parse::<i64>() {
+                Ok(value) => return Some(value),
+                Err(_) => eprintln!("'{}' is not a number", line.trim()),
+            }
+        }
+    }
+}
+
+/// Prints each output on its own line.
+pub struct StdoutOutput;
+
+impl<C: fmt::Display> OutputSink<C> for StdoutOutput {
+    fn write(&mut self, value: C) {
+        println!("{}", value);
+    }
+}
+
+/// Blocks until a value arrives. A closed channel pauses the machine.
+impl<C> InputSource<C> for Receiver<C> {
+    fn read(&mut self) -> Option<C> {
+        return self.recv().ok();
+    }
+}
+
+/// Values sent after the receiver hangs up are dropped.
+impl<C> OutputSink<C> for Sender<C> {
+    fn write(&mut self, value: C) {
+        let _ = self.send(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_queue_input() {
+        let mut inputs: VecDeque<i64> = vec![1, 2].into_iter().collect();
+
+        assert_eq!(inputs.read(), Some(1));
+        assert_eq!(inputs.read(), Some(2));
+        assert_eq!(inputs.read(), None);
+    }
+
+    #[test]
+    fn test_closure_io() {
+        let mut count = 0;
+        let mut input = FnInput(|| {
+            count += 1;
+            Some(count)
+        });
+        assert_eq!(input.read(), Some(1));
+        assert_eq!(input.read(), Some(2));
+
+        let mut written = vec![];
+        let mut output = FnOutput(|value| written.push(value * 2));
+        output.write(4);
+        assert_eq!(written, vec![8]);
+    }
+
+    #[test]
+    fn test_channel_io() {
+        let (mut sender, mut receiver) = mpsc::channel();
+
+        sender.write(5);
+        assert_eq!(receiver.read(), Some(5));
+
+        drop(sender);
+        assert_eq!(receiver.read(), None);
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::cell::Cell;
+use super::error::ErrorReason;
+
+/// Addresses below this are stored in one contiguous vector.
+pub const DENSE_LIMIT: usize = 1 << 16;
+pub const PAGE_SIZE: usize = 1024;
+/// Default ceiling on allocated cells: 16M cells, or 128MiB.
+pub const DEFAULT_LIMIT: usize = 1 << 24;
+
+/// Backing store for a machine. Unset addresses read as 0 and reading never
+/// allocates; only writes may grow the store.
+pub trait Memory {
+    type Cell: Cell;
+
+    fn get(&self, address: usize) -> Self::Cell;
+    fn set(&mut self, address: usize, value: Self::Cell) -> Result<(), ErrorReason>;
+}
+
+/// A dense vector for low addresses, where programs live, and fixed size pages
+/// allocated on first write for everything above.
+#[derive(Clone)]
+pub struct PagedMemory<C = i64> {
+    dense: Vec<C>,
+    pages: BTreeMap<usize, Box<[C]>>,
+    limit: Option<usize>,
+}
+
+impl PagedMemory {
+    pub fn new(intcode: &[i64]) -> PagedMemory {
+        return PagedMemory::with_cells(intcode);
+    }
+}
+
+impl<C: Cell> PagedMemory<C> {
+    pub fn with_cells(intcode: &[C]) -> PagedMemory<C> {
+        let mut memory = PagedMemory {
+            dense: vec![],
+            pages: BTreeMap::new(),
+            limit: Some(DEFAULT_LIMIT),
+        };
+        memory.load(0, intcode);
+        return memory;
+    }
+
+    /// Caps the number of allocated cells. Writes that would allocate past
+    /// the cap fail with `ErrorReason::MemoryLimitExceeded`.
+    pub fn set_limit(&mut self, limit: Option<usize>) {
+        self.limit = limit;
+    }
+
+    pub fn allocated(&self) -> usize {
+        return self.dense.len() + self.pages.len() * PAGE_SIZE;
+    }
+
+    /// Copies values in starting at `start`, ignoring the limit.
+    pub fn load(&mut self, start: usize, values: &[C]) {
+        let limit = self.limit.take();
+        for (offset, value) in values.iter().enumerate() {
+            let address = start + offset;
+            if address < DENSE_LIMIT && address >= self.dense.len() {
+                // keep explicit zeros in the program image
+                self.dense.resize(address + 1, C::zero());
+            }
+            self.set(address, value.clone()).unwrap();
+        }
+        self.limit = limit;
+    }
+
+    /// The dense region followed by each allocated page, in address order.
+    pub fn segments(&self) -> Vec<(usize, &[C])> {
+        let mut segments = vec![(0, self.dense.as_slice())];
+        for (start, page) in &self.pages {
+            segments.push((*start, &page[..]));
+        }
+        return segments;
+    }
+
+    fn check_limit(&self, extra: usize) -> Result<(), ErrorReason> {
+        if let Some(limit) = self.limit {
+            if self.allocated() + extra > limit {
+                return Err(ErrorReason::MemoryLimitExceeded(limit));
+            }
+        }
+        return Ok(());
+    }
+}
+
+impl<C: Cell> Memory for PagedMemory<C> {
+    type Cell = C;
+
+    fn get(&self, address: usize) -> C {
+        if address < DENSE_LIMIT {
+            return self.dense.get(address).cloned().unwrap_or_else(C::zero);
+        }
+        let start = address - address % PAGE_SIZE;
+        return match self.pages.get(&start) {
+            Some(page) => page[address - start].clone(),
+            None => C::zero(),
+        };
+    }
+
+    fn set(&mut self, address: usize, value: C) -> Result<(), ErrorReason> {
+        if address < DENSE_LIMIT {
+            if address >= self.dense.len() {
+                if value.is_zero() {
+                    return Ok(());
+                }
+                self.check_limit(address + 1 - self.dense.len())?;
+                self.dense.resize(address + 1, C::zero());
+            }
+            self.dense[address] = value;
+            return Ok(());
+        }
+
+        let start = address - address % PAGE_SIZE;
+        if !self.pages.contains_key(&start) {
+            if value.is_zero() {
+                return Ok(());
+            }
+            self.check_limit(PAGE_SIZE)?;
+            self.pages.insert(start, vec![C::zero(); PAGE_SIZE].into_boxed_slice());
+        }
+        self.pages.get_mut(&start).unwrap()[address - start] = value;
+        return Ok(());
+    }
+}
+
+/// Memory equals a vector when every address holds the same value, treating
+/// anything past the end of the vector as 0.
+impl<C: Cell> PartialEq<Vec<C>> for PagedMemory<C> {
+    fn eq(&self, other: &Vec<C>) -> bool {
+        let length = usize::max(self.dense.len(), other.len());
+        if (0..length).any(|address| self.get(address) != other.get(address).cloned().unwrap_or_else(C::zero)) {
+            return false;
+        }
+        return self.pages.iter().all(|(start, page)| {
+            page.iter().enumerate().all(|(offset, value)| *value == other.get(start + offset).cloned().unwrap_or_else(C::zero))
+        });
+    }
+}
+
+impl<C: Cell> PartialEq for PagedMemory<C> {
+    fn eq(&self, other: &PagedMemory<C>) -> bool {
+        return self.segments().iter().chain(other.segments().iter())
+            .all(|(start, values)| {
+                (*start..start + values.len()).all(|address| self.get(address) == other.get(address))
+            });
+    }
+}
+
+impl<C: Cell> fmt::Debug for PagedMemory<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut list = f.debug_list();
+        list.entries(self.dense.iter());
+        for (start, page) in &self.pages {
+            for (offset, value) in page.iter().enumerate() {
+                if !value.is_zero() {
+                    list.entry(&format_args!("[{}]: {}", start + offset, value));
+                }
+            }
+        }
+        return list.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_do_not_allocate() {
+        let memory = PagedMemory::new(&[1,2,3]);
+
+        assert_eq!(memory.get(1), 2);
+        assert_eq!(memory.get(1125899906842624), 0);
+        assert_eq!(memory.allocated(), 3);
+    }
+
+    #[test]
+    fn test_high_writes_use_pages() {
+        let mut memory = PagedMemory::new(&[99]);
+
+        memory.set(1125899906842624, 7).unwrap();
+        memory.set(1125899906842625, 8).unwrap();
+        memory.set(DENSE_LIMIT + 5, 9).unwrap();
+
+        assert_eq!(memory.get(1125899906842624), 7);
+        assert_eq!(memory.get(1125899906842625), 8);
+        assert_eq!(memory.get(DENSE_LIMIT + 5), 9);
+        assert_eq!(memory.allocated(), 1 + 2 * PAGE_SIZE);
+    }
+
+    #[test]
+    fn test_limit() {
+        let mut memory = PagedMemory::new(&[1,2,3]);
+        memory.set_limit(Some(PAGE_SIZE));
+
+        assert_eq!(memory.set(100, 1), Ok(()));
+        assert_eq!(memory.set(1 << 40, 1), Err(ErrorReason::MemoryLimitExceeded(PAGE_SIZE)));
+        assert_eq!(memory.set(1 << 40, 0), Ok(()));
+        assert_eq!(memory.set(PAGE_SIZE, 1), Err(ErrorReason::MemoryLimitExceeded(PAGE_SIZE)));
+    }
+
+    #[test]
+    fn test_equality_ignores_trailing_zeros() {
+        let mut memory = PagedMemory::new(&[1,2,0]);
+
+        assert_eq!(memory, vec![1,2]);
+        assert_eq!(memory, vec![1,2,0,0]);
+        assert!(memory != vec![1,2,3]);
+
+        memory.set(DENSE_LIMIT, 4).unwrap();
+        assert!(memory != vec![1,2]);
+    }
+}
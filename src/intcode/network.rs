@@ -0,0 +1,248 @@
+use std::error::Error;
+use std::fmt;
+use std::mem;
+
+use super::computer::{IntCodeComputer, RunState};
+use super::error::IntcodeError;
+
+/// Where a machine's outputs are delivered. Values are appended to the input
+/// queue of each receiving machine, in the order the routes were added.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Route {
+    Pipe { from: usize, to: usize },
+    /// Every output is copied to each machine in `to`.
+    FanOut { from: usize, to: Vec<usize> },
+    /// Every output is copied to all other machines.
+    Broadcast { from: usize },
+}
+
+impl Route {
+    fn from(&self) -> usize {
+        return match self {
+            Route::Pipe { from, .. } | Route::FanOut { from, .. } | Route::Broadcast { from } => *from,
+        };
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum NetworkState {
+    Halted,
+    /// Nothing can make progress and these machines are waiting for input.
+    Deadlock(Vec<usize>),
+    /// Nothing else can make progress and these machines ran out of budget.
+    BudgetExhausted(Vec<usize>),
+}
+
+/// A fault in one machine, which stops the whole network.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct NetworkError {
+    pub node: usize,
+    pub error: IntcodeError,
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "machine {}: {}", self.node, self.error);
+    }
+}
+
+impl Error for NetworkError {}
+
+/// Runs a set of machines round-robin, passing outputs along routes, until
+/// every machine halts or none can make progress.
+pub struct Network {
+    machines: Vec<IntCodeComputer>,
+    routes: Vec<Route>,
+    states: Vec<RunState>,
+    outputs: Vec<Vec<i64>>,
+}
+
+impl Network {
+    pub fn new(machines: Vec<IntCodeComputer>) -> Network {
+        let count = machines.len();
+        return Network {
+            machines,
+            routes: vec![],
+            states: vec![RunState::Running; count],
+            outputs: vec![vec![]; count],
+        };
+    }
+
+    /// Each machine feeds the next, and the last feeds the first.
+    pub fn ring(machines: Vec<IntCodeComputer>) -> Network {
+        let mut network = Network::new(machines);
+        let count = network.len();
+        for node in 0..count {
+            network.add_route(Route::Pipe { from: node, to: (node + 1) % count });
+        }
+        return network;
+    }
+
+    /// Each machine feeds the next. The last machine's outputs are only recorded.
+    pub fn pipeline(machines: Vec<IntCodeComputer>) -> Network {
+        let mut network = Network::new(machines);
+        for node in 1..network.len() {
+            network.add_route(Route::Pipe { from: node - 1, to: node });
+        }
+        return network;
+    }
+
+    pub fn add_route(&mut self, route: Route) {
+        let targets = match &route {
+            Route::Pipe { to, .. } => vec![*to],
+            Route::FanOut { to, .. } => to.clone(),
+            Route::Broadcast { .. } => vec![],
+        };
+        for node in targets.iter().chain([route.from()].iter()) {
+            assert!(*node < self.len(), "Route {:?} refers to machine {} of {}", route, node, self.len());
+        }
+        self.routes.push(route);
+    }
+
+    pub fn len(&self) -> usize {
+        return self.machines.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.machines.is_empty();
+    }
+
+    pub fn machine(&self, node: usize) -> &IntCodeComputer {
+        return &self.machines[node];
+    }
+
+    pub fn machine_mut(&mut self, node: usize) -> &mut IntCodeComputer {
+        return &mut self.machines[node];
+    }
+
+    /// How the machine stopped the last time it ran. `Running` before the first run.
+    pub fn state(&self, node: usize) -> RunState {
+        return self.states[node];
+    }
+
+    /// Every value the machine has output, whether or not it was routed anywhere.
+    pub fn outputs(&self, node: usize) -> &[i64] {
+        return &self.outputs[node];
+    }
+
+    pub fn send(&mut self, node: usize, value: i64) {
+        self.machines[node].inputs.push_back(value);
+    }
+
+    pub fn run(&mut self) -> Result<NetworkState, NetworkError> {
+        loop {
+            let mut progressed = false;
+            for node in 0..self.len() {
+                if self.states[node] == RunState::Halted {
+                    continue;
+                }
+
+                let executed = self.machines[node].executed();
+                let result = self.machines[node].run();
+                let outputs = mem::take(&mut self.machines[node].outputs);
+                self.deliver(node, &outputs);
+                self.outputs[node].extend(outputs);
+
+                self.states[node] = result.map_err(|error| NetworkError { node, error })?;
+                progressed |= self.machines[node].executed() != executed;
+            }
+
+            if !progressed {
+                return Ok(self.stuck_state());
+            }
+        }
+    }
+
+    fn deliver(&mut self, from: usize, values: &[i64]) {
+        for index in 0..self.routes.len() {
+            if self.routes[index].from() != from {
+                continue;
+            }
+            let targets = match &self.routes[index] {
+                Route::Pipe { to, .. } => vec![*to],
+                Route::FanOut { to, .. } => to.clone(),
+                Route::Broadcast { .. } => (0..self.len()).filter(|node| *node != from).collect(),
+            };
+            for target in targets {
+                self.machines[target].inputs.extend(values.iter().cloned());
+            }
+        }
+    }
+
+    fn stuck_state(&self) -> NetworkState {
+        let nodes_in = |state: RunState| -> Vec<usize> {
+            return (0..self.len()).filter(|node| self.states[*node] == state).collect();
+        };
+
+        let exhausted = nodes_in(RunState::BudgetExhausted);
+        if !exhausted.is_empty() {
+            return NetworkState::BudgetExhausted(exhausted);
+        }
+        let waiting = nodes_in(RunState::AwaitingInput);
+        if !waiting.is_empty() {
+            return NetworkState::Deadlock(waiting);
+        }
+        return NetworkState::Halted;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::error::ErrorReason;
+
+    // outputs each input plus one, until it reads 0
+    const INCREMENT: [i64; 16] = [3,15,1006,15,14,1001,15,1,15,4,15,1105,1,0,99,0];
+
+    fn machines(intcode: &[i64], count: usize) -> Vec<IntCodeComputer> {
+        return (0..count).map(|_| IntCodeComputer::new(intcode, vec![])).collect();
+    }
+
+    #[test]
+    fn test_pipeline() {
+        // doubles one input
+        let intcode = vec![3,9,1002,9,2,9,4,9,99,0];
+        let mut network = Network::pipeline(machines(&intcode, 3));
+        network.send(0, 5);
+
+        assert_eq!(network.run(), Ok(NetworkState::Halted));
+        assert_eq!(network.outputs(2), &[40]);
+        assert_eq!(network.state(1), RunState::Halted);
+    }
+
+    #[test]
+    fn test_deadlock() {
+        let intcode = vec![3,0,3,0,99];
+        let mut network = Network::ring(machines(&intcode, 2));
+        network.send(0, 1);
+
+        assert_eq!(network.run(), Ok(NetworkState::Deadlock(vec![0, 1])));
+        assert_eq!(network.state(0), RunState::AwaitingInput);
+    }
+
+    #[test]
+    fn test_fan_out_and_broadcast() {
+        let mut nodes = machines(&[104,7,104,8,99], 1);
+        nodes.extend(machines(&INCREMENT, 3));
+        let mut network = Network::new(nodes);
+        network.add_route(Route::FanOut { from: 0, to: vec![1, 2] });
+        network.add_route(Route::Broadcast { from: 3 });
+        network.send(3, 100);
+
+        assert_eq!(network.run(), Ok(NetworkState::Deadlock(vec![1, 2, 3])));
+        assert_eq!(network.outputs(1), &[8, 9, 102]);
+        assert_eq!(network.outputs(2), &[8, 9, 102]);
+        assert_eq!(network.outputs(3), &[101]);
+    }
+
+    #[test]
+    fn test_fault() {
+        let mut network = Network::ring(machines(&INCREMENT, 2));
+        network.machines[1] = IntCodeComputer::new(&[3,0,42], vec![]);
+        network.send(0, 1);
+
+        let error = network.run().unwrap_err();
+        assert_eq!(error.node, 1);
+        assert_eq!(error.error.reason, ErrorReason::UnknownOpcode);
+    }
+}
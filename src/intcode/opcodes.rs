@@ -0,0 +1,255 @@
+use std::fmt;
+use std::sync::Arc;
+
+use super::disassembler::instruction_info;
+use super::error::ErrorReason;
+use super::host::SYSCALL;
+
+/// The instruction sets of the puzzles that introduced them.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Dialect {
+    /// ADD, MUL and HLT, in position mode only.
+    Day2,
+    /// Adds IN, OUT, the jumps and comparisons, and immediate mode.
+    Day5,
+    /// Adds ARB and relative mode. The full language.
+    Day9,
+}
+
+/// What a registered instruction does once its handler has run.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Effect<C = i64> {
+    /// Carries on with the next instruction.
+    Continue,
+    /// Stores the value through the write parameter, then carries on. Faults
+    /// with `ErrorReason::NoWriteParameter` if the instruction has none.
+    Write(C),
+    /// Sends the value to the output, then carries on.
+    Output(C),
+    Jump(i64),
+    Halt,
+}
+
+/// Runs a registered instruction, given the values of its parameters other
+/// than the write parameter, in order.
+pub type Handler<C> = Arc<dyn Fn(&[C]) -> Result<Effect<C>, ErrorReason> + Send + Sync>;
+
+#[derive(Clone)]
+pub(crate) enum Behaviour<C> {
+    /// One of the machine's own instructions, by its standard operation.
+    Builtin(i64),
+    Registered(Handler<C>),
+}
+
+#[derive(Clone)]
+pub(crate) struct Opcode<C> {
+    pub(crate) mnemonic: &'static str,
+    pub(crate) parameters: usize,
+    /// Which parameter, counting from 0, is written to.
+    pub(crate) write: Option<usize>,
+    pub(crate) behaviour: Behaviour<C>,
+}
+
+/// The instructions a machine understands, by operation (the opcode less its
+/// modes), and the highest parameter mode it accepts.
+#[derive(Clone)]
+pub struct OpcodeTable<C = i64> {
+    opcodes: Vec<Option<Opcode<C>>>,
+    max_mode: i64,
+    standard: bool,
+}
+
+impl<C> OpcodeTable<C> {
+    pub fn dialect(dialect: Dialect) -> OpcodeTable<C> {
+        let (operations, max_mode): (&[i64], i64) = match dialect {
+            Dialect::Day2 => (&[1, 2, 99], 0),
+            Dialect::Day5 => (&[1, 2, 3, 4, 5, 6, 7, 8, 99], 1),
+            Dialect::Day9 => (&[1, 2, 3, 4, 5, 6, 7, 8, 9, 99], 2),
+        };
+
+        let mut opcodes: Vec<Option<Opcode<C>>> = (0..100).map(|_| None).collect();
+        for operation in operations {
+            let (mnemonic, parameters) = instruction_info(*operation).unwrap();
+            let write = match operation {
+                3 => Some(0),
+                1 | 2 | 7 | 8 => Some(2),
+                _ => None,
+            };
+            opcodes[*operation as usize] = Some(Opcode { mnemonic, parameters, write, behaviour: Behaviour::Builtin(*operation) });
+        }
+        return OpcodeTable { opcodes, max_mode, standard: dialect == Dialect::Day9 };
+    }
+
+    /// Adds an instruction, or replaces the one already at `operation`.
+    /// Panics unless `operation` is 1 to 99 and there are at most three
+    /// parameters, since opcodes have room for no more modes than that.
+    pub fn register<F>(&mut self, operation: i64, mnemonic: &'static str, parameters: usize, write: Option<usize>, handler: F) -> &mut OpcodeTable<C>
+        where F: Fn(&[C]) -> Result<Effect<C>, ErrorReason> + Send + Sync + 'static {
+        assert!((1..100).contains(&operation), "Operation {} does not fit in an opcode", operation);
+        assert!(parameters <= 3, "Opcodes have modes for at most 3 parameters");
+        assert!(write.is_none_or(|write| write < parameters), "The write parameter must be one of the parameters");
+
+        let behaviour = Behaviour::Registered(Arc::new(handler));
+        self.opcodes[operation as usize] = Some(Opcode { mnemonic, parameters, write, behaviour });
+        self.standard = false;
+        return self;
+    }
+
+    /// Adds `SYS`, which calls the host services registered on the machine
+    /// with `IntCodeComputer::register_host_call`.
+    pub fn enable_host_calls(&mut self) -> &mut OpcodeTable<C> {
+        let behaviour = Behaviour::Builtin(SYSCALL);
+        self.opcodes[SYSCALL as usize] = Some(Opcode { mnemonic: "SYS", parameters: 3, write: Some(2), behaviour });
+        self.standard = false;
+        return self;
+    }
+
+    /// Mnemonic and parameter count for an operation in this table.
+    pub fn info(&self, operation: i64) -> Option<(&'static str, usize)> {
+        return self.get(operation).map(|opcode| (opcode.mnemonic, opcode.parameters));
+    }
+
+    /// The operation with this mnemonic, ignoring case.
+    pub fn operation(&self, mnemonic: &str) -> Option<i64> {
+        return self.opcodes.iter()
+            .position(|opcode| opcode.as_ref().is_some_and(|opcode| opcode.mnemonic.eq_ignore_ascii_case(mnemonic)))
+            .map(|operation| operation as i64);
+    }
+
+    pub(crate) fn get(&self, operation: i64) -> Option<&Opcode<C>> {
+        if !(0..100).contains(&operation) {
+            return None;
+        }
+        return self.opcodes[operation as usize].as_ref();
+    }
+
+    pub fn max_mode(&self) -> i64 {
+        return self.max_mode;
+    }
+
+    /// Whether this is exactly the day 9 instruction set, which is all the
+    /// compiled backend knows how to translate.
+    pub fn is_standard(&self) -> bool {
+        return self.standard;
+    }
+}
+
+impl<C> Default for OpcodeTable<C> {
+    fn default() -> OpcodeTable<C> {
+        return OpcodeTable::dialect(Dialect::Day9);
+    }
+}
+
+impl<C> fmt::Debug for OpcodeTable<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mnemonics: Vec<(usize, &str)> = self.opcodes.iter().enumerate()
+            .filter_map(|(operation, opcode)| opcode.as_ref().map(|opcode| (operation, opcode.mnemonic)))
+            .collect();
+        return f.debug_struct("OpcodeTable").field("opcodes", &mnemonics).field("max_mode", &self.max_mode).finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use super::super::compiled::CompiledComputer;
+    use super::super::computer::{IntCodeComputer, RunState};
+    use super::super::error::IntcodeError;
+
+    #[test]
+    fn test_dialects() {
+        let day_two: OpcodeTable = OpcodeTable::dialect(Dialect::Day2);
+        let day_five: OpcodeTable = OpcodeTable::dialect(Dialect::Day5);
+
+        assert_eq!(day_two.info(2), Some(("MUL", 3)));
+        assert_eq!(day_two.info(3), None);
+        assert_eq!(day_five.info(4), Some(("OUT", 1)));
+        assert_eq!(day_five.info(9), None);
+        assert_eq!((day_two.max_mode(), day_five.max_mode()), (0, 1));
+        assert!(OpcodeTable::<i64>::default().is_standard() && !day_five.is_standard());
+    }
+
+    #[test]
+    fn test_dialect_rejects_later_features() {
+        let mut computer = IntCodeComputer::new(&[104,1,99], vec![]);
+        computer.set_opcodes(OpcodeTable::dialect(Dialect::Day2));
+        assert_eq!(computer.run(), Err(IntcodeError::new(0, 104, ErrorReason::UnknownOpcode)));
+
+        let mut computer = IntCodeComputer::new(&[1101,1,1,0,99], vec![]);
+        computer.set_opcodes(OpcodeTable::dialect(Dialect::Day2));
+        assert_eq!(computer.run(), Err(IntcodeError::new(0, 1101, ErrorReason::UnknownParameterMode(1))));
+
+        let mut computer = IntCodeComputer::new(&[204,0,99], vec![]);
+        computer.set_opcodes(OpcodeTable::dialect(Dialect::Day5));
+        assert_eq!(computer.run(), Err(IntcodeError::new(0, 204, ErrorReason::UnknownParameterMode(2))));
+    }
+
+    #[test]
+    fn test_registered_instructions() {
+        let printed = Arc::new(Mutex::new(vec![]));
+        let log = printed.clone();
+        let mut opcodes = OpcodeTable::default();
+        opcodes
+            .register(50, "PRINT", 1, None, move |values: &[i64]| {
+                log.lock().unwrap().push(values[0]);
+                return Ok(Effect::Continue);
+            })
+            // SUB a b c stores a - b in c
+            .register(51, "SUB", 3, Some(2), |values: &[i64]| Ok(Effect::Write(values[0] - values[1])))
+            .register(52, "DIV", 3, Some(2), |values: &[i64]| match values[1] {
+                0 => Err(ErrorReason::Overflow),
+                divisor => Ok(Effect::Write(values[0] / divisor)),
+            });
+
+        let mut computer = IntCodeComputer::new(&[1151,10,3,0,50,0,204,0,11152,1,0,99], vec![]);
+        computer.set_opcodes(opcodes.clone());
+        let result = computer.run();
+
+        assert_eq!(*printed.lock().unwrap(), vec![7]);
+        assert_eq!(computer.outputs, vec![7]);
+        assert_eq!(result, Err(IntcodeError::new(8, 11152, ErrorReason::Overflow)));
+        assert_eq!(computer.opcodes().info(51), Some(("SUB", 3)));
+        assert_eq!(computer.opcodes().operation("sub"), Some(51));
+    }
+
+    #[test]
+    fn test_registered_control_flow() {
+        let mut opcodes = OpcodeTable::default();
+        opcodes.register(60, "JMP", 1, None, |values: &[i64]| Ok(Effect::Jump(values[0])));
+        opcodes.register(61, "STOP", 0, None, |_: &[i64]| Ok(Effect::Halt));
+        opcodes.register(62, "EMIT", 1, None, |values: &[i64]| Ok(Effect::Output(values[0] * 10)));
+
+        // jumps over two cells of data to EMIT 3, then stops
+        let mut computer = IntCodeComputer::new(&[1160,4,1,1,162,3,61], vec![]);
+        computer.set_opcodes(opcodes);
+
+        assert_eq!(computer.run_until_output(), Ok(RunState::OutputReady));
+        assert_eq!(computer.run(), Ok(RunState::Halted));
+        assert_eq!(computer.outputs, vec![30]);
+    }
+
+    #[test]
+    fn test_write_without_write_parameter() {
+        let mut opcodes = OpcodeTable::default();
+        opcodes.register(70, "BAD", 1, None, |values: &[i64]| Ok(Effect::Write(values[0])));
+
+        let mut computer = IntCodeComputer::new(&[1170,5,99], vec![]);
+        computer.set_opcodes(opcodes);
+
+        assert_eq!(computer.run(), Err(IntcodeError::new(0, 1170, ErrorReason::NoWriteParameter)));
+    }
+
+    #[test]
+    fn test_compiled_backend_uses_the_table() {
+        // ADD in a table where it subtracts instead
+        let mut opcodes = OpcodeTable::default();
+        opcodes.register(1, "ADD", 3, Some(2), |values: &[i64]| Ok(Effect::Write(values[0] - values[1])));
+
+        let mut computer = CompiledComputer::new(&[1101,5,3,7,4,7,99,0], vec![]);
+        computer.set_opcodes(opcodes);
+
+        assert_eq!(computer.run(), Ok(RunState::Halted));
+        assert_eq!(computer.outputs, vec![2]);
+    }
+}
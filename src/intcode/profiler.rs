@@ -0,0 +1,192 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use super::cell::Cell;
+use super::disassembler::decode;
+use super::trace::{TraceEvent, TraceSink};
+
+/// Counts executions per address and per operation, taken backward jumps, and
+/// time spent under each estimated call stack. Give it to a machine with
+/// `set_tracer`, wrapped in `Arc<Mutex<_>>` to read it afterwards.
+///
+/// Intcode has no calls, but compiled programs open a stack frame by raising
+/// the relative base on entry to a function and lowering it again before
+/// returning. Each raise is taken as a call to a function named after the
+/// address of the ARB, and each lowering as a return.
+#[derive(Debug, Default, Clone)]
+pub struct Profiler {
+    addresses: BTreeMap<usize, u64>,
+    operations: BTreeMap<&'static str, u64>,
+    /// Taken jumps by (target, jump address), where the target is not after the jump.
+    loops: BTreeMap<(usize, usize), u64>,
+    stacks: BTreeMap<Vec<usize>, u64>,
+    stack: Vec<usize>,
+    max_depth: usize,
+    instructions: u64,
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        return Profiler::default();
+    }
+
+    pub fn instructions(&self) -> u64 {
+        return self.instructions;
+    }
+
+    pub fn max_depth(&self) -> usize {
+        return self.max_depth;
+    }
+
+    /// Addresses by times executed, busiest first.
+    pub fn hot_addresses(&self) -> Vec<(usize, u64)> {
+        let mut addresses: Vec<(usize, u64)> = self.addresses.iter().map(|(address, count)| (*address, *count)).collect();
+        addresses.sort_by_key(|(address, count)| (Reverse(*count), *address));
+        return addresses;
+    }
+
+    /// Mnemonics by times executed, busiest first.
+    pub fn operations(&self) -> Vec<(&'static str, u64)> {
+        let mut operations: Vec<(&'static str, u64)> = self.operations.iter().map(|(mnemonic, count)| (*mnemonic, *count)).collect();
+        operations.sort_by_key(|(mnemonic, count)| (Reverse(*count), *mnemonic));
+        return operations;
+    }
+
+    /// Backward jumps as (first address, jump address, times taken, instructions
+    /// executed in between), by instructions executed.
+    pub fn hot_loops(&self) -> Vec<(usize, usize, u64, u64)> {
+        let mut loops: Vec<(usize, usize, u64, u64)> = self.loops.iter()
+            .map(|((start, end), taken)| (*start, *end, *taken, self.addresses.range(start..=end).map(|(_, count)| count).sum()))
+            .collect();
+        loops.sort_by_key(|(start, end, _, executed)| (Reverse(*executed), *start, *end));
+        return loops;
+    }
+
+    /// One line per call stack in the folded format flamegraph tools read,
+    /// `main;sub_15;sub_904 1234`, counting instructions.
+    pub fn folded(&self) -> String {
+        return self.stacks.iter()
+            .map(|(stack, count)| {
+                let frames: String = stack.iter().map(|address| format!(";sub_{}", address)).collect();
+                format!("main{} {}\n", frames, count)
+            })
+            .collect();
+    }
+
+    /// The `top` busiest addresses and loops, and every operation, with
+    /// instructions decoded from `intcode`.
+    pub fn report(&self, intcode: &[i64], top: usize) -> String {
+        let total = self.instructions.max(1) as f64;
+        let mut report = format!("{} instructions executed, call depth up to {}\n", self.instructions, self.max_depth);
+
+        report += "\nHot addresses\n";
+        for (address, count) in self.hot_addresses().into_iter().take(top) {
+            let line = match decode(intcode, address) {
+                Some(line) => line.to_string(),
+                None => format!("{:>5}: ?", address),
+            };
+            report += &format!("{:>10} {:>6.2}% {}\n", count, count as f64 / total * 100.0, line);
+        }
+
+        report += "\nOperations\n";
+        for (mnemonic, count) in self.operations() {
+            report += &format!("{:>10} {:>6.2}% {}\n", count, count as f64 / total * 100.0, mnemonic);
+        }
+
+        report += "\nHot loops\n";
+        for (start, end, taken, executed) in self.hot_loops().into_iter().take(top) {
+            report += &format!("{:>10} {:>6.2}% {}..{}, {} iterations\n", executed, executed as f64 / total * 100.0, start, end, taken);
+        }
+        return report;
+    }
+}
+
+impl<C: Cell> TraceSink<C> for Profiler {
+    fn record(&mut self, event: &TraceEvent<C>) {
+        self.instructions += 1;
+        *self.addresses.entry(event.ptr).or_default() += 1;
+        *self.operations.entry(event.mnemonic).or_default() += 1;
+
+        // the ARB opening a frame counts to the new frame, and the one closing it to the old
+        let delta = event.relative_base.map(|(old, new)| new - old).unwrap_or(0);
+        if delta > 0 {
+            self.stack.push(event.ptr);
+            self.max_depth = self.max_depth.max(self.stack.len());
+        }
+        *self.stacks.entry(self.stack.clone()).or_default() += 1;
+        if delta < 0 {
+            self.stack.pop();
+        }
+
+        let jumps_if = match event.mnemonic {
+            "JT" => true,
+            "JF" => false,
+            _ => return,
+        };
+        let (condition, target) = (&event.operands[0], event.operands[1].to_i64());
+        if let Some(target) = target {
+            if condition.is_zero() != jumps_if && target >= 0 && target as usize <= event.ptr {
+                *self.loops.entry((target as usize, event.ptr)).or_default() += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use super::super::computer::IntCodeComputer;
+
+    fn profile(intcode: &[i64], inputs: Vec<i64>) -> Profiler {
+        let profiler = Arc::new(Mutex::new(Profiler::new()));
+        let mut computer = IntCodeComputer::new(intcode, inputs);
+        computer.set_tracer(Box::new(profiler.clone()));
+        computer.run().unwrap();
+
+        return profiler.lock().unwrap().clone();
+    }
+
+    #[test]
+    fn test_counts_and_loops() {
+        // counts address 10 down from 3, then halts
+        let intcode = vec![4,10,1001,10,-1,10,1005,10,0,99,3];
+
+        let profiler = profile(&intcode, vec![]);
+
+        assert_eq!(profiler.instructions(), 10);
+        assert_eq!(profiler.hot_addresses(), vec![(0, 3), (2, 3), (6, 3), (9, 1)]);
+        assert_eq!(profiler.operations(), vec![("ADD", 3), ("JT", 3), ("OUT", 3), ("HLT", 1)]);
+        assert_eq!(profiler.hot_loops(), vec![(0, 6, 2, 9)]);
+    }
+
+    #[test]
+    fn test_folded_stacks() {
+        let intcode = vec![109,5,109,3,109,-3,109,-5,99];
+        let expected = "\
+main 1
+main;sub_0 2
+main;sub_0;sub_2 2
+";
+
+        let profiler = profile(&intcode, vec![]);
+
+        assert_eq!(profiler.folded(), expected);
+        assert_eq!(profiler.max_depth(), 2);
+    }
+
+    #[test]
+    fn test_boost() {
+        let intcode = super::super::parse(include_str!("../../resources/9.input"));
+
+        let profiler = profile(&intcode, vec![2]);
+        let total: u64 = profiler.folded().lines()
+            .map(|line| line.rsplit(' ').next().unwrap().parse::<u64>().unwrap())
+            .sum();
+
+        assert_eq!(total, profiler.instructions());
+        assert!(profiler.max_depth() > 10);
+        assert!(!profiler.hot_loops().is_empty());
+    }
+}
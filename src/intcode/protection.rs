@@ -0,0 +1,228 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::ops::Range;
+
+use super::cfg::control_flow_graph_from;
+use super::disassembler::{Line, Operand};
+
+/// What the machine does when a program breaks the code map.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Policy {
+    /// Keep running and record the violation.
+    Report,
+    /// Record the violation and print it to stderr.
+    Warn,
+    /// Stop with `ErrorReason::Protection` before the offending write or
+    /// instruction takes effect.
+    Fault,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum ViolationKind {
+    /// A write to this code address.
+    CodeWrite(usize),
+    /// A write to the opcode or an operand of the instruction about to run
+    /// next, at this address.
+    NextInstructionWrite(usize),
+    /// Executing an address that is not code.
+    ExecutedData,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct Violation {
+    /// The instruction that broke the code map.
+    pub ptr: usize,
+    pub kind: ViolationKind,
+}
+
+/// A map of which addresses hold code, and the violations of it seen so far.
+#[derive(Debug, Clone)]
+pub struct Protection {
+    policy: Policy,
+    code: BTreeSet<usize>,
+    violations: Vec<Violation>,
+    seen: BTreeSet<Violation>,
+    /// The cells of the instruction after the one executing, while one is.
+    pub(crate) next: Option<Range<usize>>,
+}
+
+impl Protection {
+    /// Marks the cells of every instruction statically reachable from
+    /// address 0 as code. Everything else, in the program or beyond it, is data.
+    ///
+    /// Returns are jumps through memory, so each constant the code stores
+    /// with an ADD or MUL of two immediates, the way a caller pushes its
+    /// return address, is also followed if it lies inside the program.
+    pub fn for_program(intcode: &[i64], policy: Policy) -> Protection {
+        let mut roots = BTreeSet::new();
+        roots.insert(0);
+        loop {
+            let cfg = control_flow_graph_from(intcode, &roots.iter().cloned().collect::<Vec<usize>>());
+            let lines: Vec<Line> = cfg.blocks.into_iter().flat_map(|block| block.lines).collect();
+            let found = lines.iter().filter_map(stored_constant)
+                .filter(|address| *address < intcode.len() && !roots.contains(address))
+                .collect::<Vec<usize>>();
+
+            if found.is_empty() {
+                let mut code = BTreeSet::new();
+                for line in lines {
+                    if let Line::Instruction { .. } = line {
+                        code.extend(line.address()..line.address() + line.size());
+                    }
+                }
+                return Protection::with_code(code, policy);
+            }
+            roots.extend(found);
+        }
+    }
+
+    pub fn with_code(code: BTreeSet<usize>, policy: Policy) -> Protection {
+        return Protection { policy, code, violations: vec![], seen: BTreeSet::new(), next: None };
+    }
+
+    pub fn is_code(&self, address: usize) -> bool {
+        return self.code.contains(&address);
+    }
+
+    /// Oldest first, each instruction's violation of each kind once however
+    /// often it runs. With `Policy::Fault` this holds at most the violation
+    /// that stopped the machine.
+    pub fn violations(&self) -> &[Violation] {
+        return &self.violations;
+    }
+
+    /// The violation a write by the instruction at `ptr` would make, if any.
+    pub(crate) fn check_write(&self, ptr: usize, address: usize) -> Option<Violation> {
+        let kind = match &self.next {
+            Some(next) if next.contains(&address) => ViolationKind::NextInstructionWrite(address),
+            Some(_) if self.is_code(address) => ViolationKind::CodeWrite(address),
+            _ => return None,
+        };
+        return Some(Violation { ptr, kind });
+    }
+
+    pub(crate) fn check_execute(&self, ptr: usize) -> Option<Violation> {
+        if self.is_code(ptr) {
+            return None;
+        }
+        return Some(Violation { ptr, kind: ViolationKind::ExecutedData });
+    }
+
+    /// Records a violation, unless it was seen before. Returns true if the
+    /// machine should fault.
+    pub(crate) fn record(&mut self, violation: Violation) -> bool {
+        if self.seen.insert(violation) {
+            if self.policy == Policy::Warn {
+                eprintln!("Warning: {}", violation);
+            }
+            self.violations.push(violation);
+        }
+        return self.policy == Policy::Fault;
+    }
+}
+
+fn stored_constant(line: &Line) -> Option<usize> {
+    if let Line::Instruction { mnemonic, operands, .. } = line {
+        if let (Operand::Immediate(left), Operand::Immediate(right)) = (operands.first()?, operands.get(1)?) {
+            let value = match *mnemonic {
+                "ADD" => left.checked_add(*right)?,
+                "MUL" => left.checked_mul(*right)?,
+                _ => return None,
+            };
+            return if value >= 0 { Some(value as usize) } else { None };
+        }
+    }
+    return None;
+}
+
+impl fmt::Display for ViolationKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            ViolationKind::CodeWrite(address) => write!(f, "write to code at {}", address),
+            ViolationKind::NextInstructionWrite(address) => write!(f, "write to the next instruction at {}", address),
+            ViolationKind::ExecutedData => write!(f, "executed data"),
+        };
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{} by instruction at {}", self.kind, self.ptr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::computer::IntCodeComputer;
+    use super::super::error::ErrorReason;
+
+    fn run(intcode: &[i64], inputs: Vec<i64>, policy: Policy) -> IntCodeComputer {
+        let mut computer = IntCodeComputer::new(intcode, inputs);
+        computer.set_protection(Some(Protection::for_program(intcode, policy)));
+        let _ = computer.run();
+        return computer;
+    }
+
+    fn kinds(computer: &IntCodeComputer) -> Vec<ViolationKind> {
+        return computer.protection().unwrap().violations().iter().map(|violation| violation.kind).collect();
+    }
+
+    #[test]
+    fn test_well_behaved_program() {
+        let intcode = super::super::parse(include_str!("../../resources/9.input"));
+
+        let computer = run(&intcode, vec![1], Policy::Fault);
+
+        assert_eq!(computer.outputs, vec![2204990589]);
+        assert_eq!(kinds(&computer), vec![]);
+    }
+
+    #[test]
+    fn test_next_instruction_write() {
+        // writes the operand of the OUT that follows
+        let intcode = vec![1101,7,0,5,104,0,99];
+
+        let computer = run(&intcode, vec![], Policy::Report);
+
+        assert_eq!(computer.outputs, vec![7]);
+        assert_eq!(kinds(&computer), vec![ViolationKind::NextInstructionWrite(5)]);
+    }
+
+    #[test]
+    fn test_repeated_violations_are_recorded_once() {
+        // rewrites the operand of a later OUT, three times round a loop
+        let intcode = vec![1101,7,0,9,1001,14,-1,14,104,0,1005,14,0,99,3];
+
+        let computer = run(&intcode, vec![], Policy::Report);
+
+        assert_eq!(computer.outputs, vec![7, 7, 7]);
+        assert_eq!(kinds(&computer), vec![ViolationKind::CodeWrite(9)]);
+    }
+
+    #[test]
+    fn test_fault_stops_before_code_write() {
+        // rewrites the HLT at address 6 into an ADD
+        let intcode = vec![1101,1,0,6,104,0,99];
+
+        let mut computer = IntCodeComputer::new(&intcode, vec![]);
+        computer.set_protection(Some(Protection::for_program(&intcode, Policy::Fault)));
+        let error = computer.run().unwrap_err();
+
+        assert_eq!((error.ptr, error.reason), (0, ErrorReason::Protection(ViolationKind::CodeWrite(6))));
+        assert_eq!(computer.peek(6), 99);
+    }
+
+    #[test]
+    fn test_day_five_modifies_itself() {
+        // day 5 turns the data at address 6 into the instruction it runs next
+        let intcode = super::super::parse(include_str!("../../resources/5.input"));
+
+        let computer = run(&intcode, vec![1], Policy::Report);
+
+        let violations = computer.protection().unwrap().violations();
+        assert_eq!(violations[0], Violation { ptr: 2, kind: ViolationKind::NextInstructionWrite(6) });
+        assert_eq!(violations[1], Violation { ptr: 6, kind: ViolationKind::ExecutedData });
+        assert_eq!(computer.outputs.last(), Some(&9938601));
+    }
+}
@@ -0,0 +1,44 @@
+/// A small xorshift generator, so that fuzzing and host calls can be
+/// reproduced from a seed without pulling in a dependency.
+#[derive(Debug, Clone)]
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Rng {
+        // xorshift is stuck at zero, so the one seed that mixes to zero
+        // starts somewhere else instead
+        let state = match seed ^ 0x9e37_79b9_7f4a_7c15 {
+            0 => 0xbf58_476d_1ce4_e5b9,
+            state => state,
+        };
+        return Rng { state };
+    }
+
+    pub(crate) fn next(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        return self.state.wrapping_mul(0x2545_f491_4f6c_dd1d);
+    }
+
+    /// Uniform enough below `bound`, which must not be 0.
+    pub(crate) fn below(&mut self, bound: u64) -> u64 {
+        return self.next() % bound;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_seed_is_stuck_at_zero() {
+        let mut rng = Rng::new(0x9e37_79b9_7f4a_7c15);
+
+        let values: Vec<u64> = (0..4).map(|_| rng.next()).collect();
+
+        assert!(values.iter().all(|value| *value != 0), "{:?}", values);
+    }
+}
@@ -0,0 +1,170 @@
+use std::fs;
+use std::io;
+
+use super::memory::PagedMemory;
+
+const HEADER: &str = "intcode-snapshot 1";
+
+/// A copy of a machine's memory and registers. Input and output queues are
+/// not included; they belong to whoever is driving the machine.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Snapshot<M = PagedMemory> {
+    pub intcode: M,
+    pub ptr: usize,
+    pub relative_base: i64,
+}
+
+impl Snapshot {
+    /// Plain text, one field per line, so saved states can be diffed and read.
+    /// Memory beyond the dense region follows as one `page <address> ...` line
+    /// per allocated page.
+    pub fn to_text(&self) -> String {
+        let segments = self.intcode.segments();
+        let mut text = format!("{}\nptr {}\nrelative_base {}\nmemory {}\n", HEADER, self.ptr, self.relative_base, values(segments[0].1));
+        for (start, page) in &segments[1..] {
+            text += &format!("page {} {}\n", start, values(page));
+        }
+        return text;
+    }
+
+    pub fn from_text(text: &str) -> io::Result<Snapshot> {
+        let mut lines = text.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(invalid(format!("expected '{}' header", HEADER)));
+        }
+
+        let ptr = field(lines.next(), "ptr")?;
+        let relative_base = field(lines.next(), "relative_base")?;
+        let mut intcode = PagedMemory::new(&parse_values(field(lines.next(), "memory")?)?);
+        for line in lines {
+            let page = field(Some(line), "page")?;
+            let mut parts = page.splitn(2, ' ');
+            let start = parts.next().unwrap_or("");
+            let start = start.parse().map_err(|_| invalid(format!("bad page address '{}'", start)))?;
+            intcode.load(start, &parse_values(parts.next().unwrap_or(""))?);
+        }
+
+        return Ok(Snapshot {
+            intcode,
+            ptr: ptr.parse().map_err(|_| invalid(format!("bad ptr '{}'", ptr)))?,
+            relative_base: relative_base.parse().map_err(|_| invalid(format!("bad relative_base '{}'", relative_base)))?,
+        });
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        return fs::write(path, self.to_text());
+    }
+
+    pub fn load(path: &str) -> io::Result<Snapshot> {
+        return Snapshot::from_text(&fs::read_to_string(path)?);
+    }
+}
+
+fn values(values: &[i64]) -> String {
+    let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+    return values.join(",");
+}
+
+fn parse_values(text: &str) -> io::Result<Vec<i64>> {
+    if text.is_empty() {
+        return Ok(vec![]);
+    }
+    return text.split(',')
+        .map(|value| value.parse::<i64>().map_err(|error| invalid(format!("bad memory value: {}", error))))
+        .collect();
+}
+
+fn field<'a>(line: Option<&'a str>, name: &str) -> io::Result<&'a str> {
+    let line = line.ok_or_else(|| invalid(format!("missing '{}' line", name)))?;
+    let mut parts = line.splitn(2, ' ');
+    if parts.next() != Some(name) {
+        return Err(invalid(format!("expected '{}' but found '{}'", name, line)));
+    }
+    return Ok(parts.next().unwrap_or(""));
+}
+
+fn invalid(message: String) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, message);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::computer::{IntCodeComputer, RunState};
+    use super::super::memory::Memory;
+
+    #[test]
+    fn test_snapshot_and_restore() {
+        // doubles each input, forever
+        let intcode = vec![3,20,1002,20,2,20,4,20,1105,1,0];
+
+        let mut computer = IntCodeComputer::new(&intcode, vec![5]);
+        assert_eq!(computer.run(), Ok(RunState::AwaitingInput));
+        let checkpoint = computer.snapshot();
+
+        computer.inputs.push_back(7);
+        computer.run().unwrap();
+        computer.restore(&checkpoint);
+        computer.inputs.push_back(8);
+        computer.run().unwrap();
+
+        assert_eq!(computer.outputs, vec![10, 14, 16]);
+        assert_eq!(checkpoint.ptr, 0);
+    }
+
+    #[test]
+    fn test_clone_branches_independently() {
+        let intcode = vec![3,0,4,0,99];
+
+        let mut computer = IntCodeComputer::new(&intcode, vec![]);
+        computer.run().unwrap();
+        let mut branch = computer.clone();
+        computer.inputs.push_back(1);
+        branch.inputs.push_back(2);
+        computer.run().unwrap();
+        branch.run().unwrap();
+
+        assert_eq!(computer.outputs, vec![1]);
+        assert_eq!(branch.outputs, vec![2]);
+    }
+
+    #[test]
+    fn test_text_round_trip() {
+        let snapshot = Snapshot { intcode: PagedMemory::new(&[109,-3,204,1,99]), ptr: 2, relative_base: -3 };
+        let expected = "intcode-snapshot 1\nptr 2\nrelative_base -3\nmemory 109,-3,204,1,99\n";
+
+        assert_eq!(snapshot.to_text(), expected);
+        assert_eq!(Snapshot::from_text(expected).unwrap(), snapshot);
+    }
+
+    #[test]
+    fn test_text_round_trip_with_pages() {
+        let mut computer = IntCodeComputer::new(&[1101,5,6,1125899906842624,99], vec![]);
+        computer.run().unwrap();
+        let snapshot = computer.snapshot();
+
+        let text = snapshot.to_text();
+        let restored = Snapshot::from_text(&text).unwrap();
+
+        assert_eq!(text.lines().count(), 5);
+        assert_eq!(restored.intcode.get(1125899906842624), 11);
+        assert_eq!(restored, snapshot);
+    }
+
+    #[test]
+    fn test_invalid_text() {
+        let cases = vec![
+            "",
+            "intcode-snapshot 2\nptr 0\nrelative_base 0\nmemory 99\n",
+            "intcode-snapshot 1\nptr x\nrelative_base 0\nmemory 99\n",
+            "intcode-snapshot 1\nptr 0\nmemory 99\n",
+            "intcode-snapshot 1\nptr 0\nrelative_base 0\nmemory 9,,9\n",
+            "intcode-snapshot 1\nptr 0\nrelative_base 0\nmemory 99\npage x 1\n",
+        ];
+
+        for text in cases {
+            let error = Snapshot::from_text(text).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "\nFailed on {:?}\n", text);
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use super::budget::Budget;
+use super::computer::{IntCodeComputer, RunState};
+use super::error::IntcodeError;
+use super::io::{InputSource, OutputSink};
+use super::memory::PagedMemory;
+use super::network::NetworkError;
+
+/// Instructions a worker runs between checks of the stop flag.
+const CHUNK: u64 = 10_000;
+/// How often a worker blocked on input checks the stop flag.
+const POLL: Duration = Duration::from_millis(10);
+
+/// Reads from a channel, giving up when every sender has hung up or the
+/// runtime is stopped.
+struct ChannelInput {
+    receiver: Receiver<i64>,
+    stop: Arc<AtomicBool>,
+}
+
+impl InputSource for ChannelInput {
+    fn read(&mut self) -> Option<i64> {
+        loop {
+            match self.receiver.recv_timeout(POLL) {
+                Ok(value) => return Some(value),
+                Err(RecvTimeoutError::Disconnected) => return None,
+                Err(RecvTimeoutError::Timeout) => {
+                    if self.stop.load(Ordering::SeqCst) {
+                        return None;
+                    }
+                },
+            }
+        }
+    }
+}
+
+/// Sends each value to every connected machine and keeps a copy.
+struct ChannelOutput {
+    senders: Vec<Sender<i64>>,
+    log: Vec<i64>,
+}
+
+impl OutputSink for ChannelOutput {
+    fn write(&mut self, value: i64) {
+        for sender in &self.senders {
+            // the receiving machine may already have finished
+            let _ = sender.send(value);
+        }
+        self.log.push(value);
+    }
+}
+
+struct Node {
+    intcode: Vec<i64>,
+    inputs: Vec<i64>,
+    targets: Vec<usize>,
+}
+
+/// Describes machines and the channels between them. `start` runs each
+/// machine on its own thread.
+///
+/// Once any machine halts or faults the runtime stops: the rest take what is
+/// already on their inputs, then finish when they next wait for input or
+/// within a few thousand instructions, instead of running forever.
+#[derive(Default)]
+pub struct Runtime {
+    nodes: Vec<Node>,
+}
+
+/// What a machine left behind when its thread finished.
+#[derive(Debug, PartialEq)]
+pub struct MachineResult {
+    /// `AwaitingInput` if it stopped for lack of input, or `BudgetExhausted`
+    /// if it was still running when the runtime was stopped.
+    pub state: RunState,
+    pub intcode: PagedMemory,
+    pub outputs: Vec<i64>,
+}
+
+impl Runtime {
+    pub fn new() -> Runtime {
+        return Runtime::default();
+    }
+
+    /// Adds a machine with values already queued on its input. Returns its index.
+    pub fn add_machine(&mut self, intcode: &[i64], inputs: Vec<i64>) -> usize {
+        self.nodes.push(Node { intcode: intcode.to_vec(), inputs, targets: vec![] });
+        return self.nodes.len() - 1;
+    }
+
+    /// Sends every output of `from` to the input of `to`.
+    pub fn connect(&mut self, from: usize, to: usize) {
+        assert!(to < self.nodes.len(), "No machine {} to connect to", to);
+        self.nodes[from].targets.push(to);
+    }
+
+    pub fn start(self) -> RuntimeHandle {
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut senders = vec![];
+        let mut receivers = vec![];
+        for node in &self.nodes {
+            let (sender, receiver) = mpsc::channel();
+            for input in &node.inputs {
+                sender.send(*input).unwrap();
+            }
+            senders.push(sender);
+            receivers.push(receiver);
+        }
+
+        let mut threads = vec![];
+        for (node, receiver) in self.nodes.into_iter().zip(receivers) {
+            let inputs = ChannelInput { receiver, stop: stop.clone() };
+            let outputs = ChannelOutput {
+                senders: node.targets.iter().map(|target| senders[*target].clone()).collect(),
+                log: vec![],
+            };
+            let computer = IntCodeComputer::with_io(&node.intcode, inputs, outputs);
+            let stop = stop.clone();
+            threads.push(thread::spawn(move || run_worker(computer, stop)));
+        }
+
+        return RuntimeHandle { threads, stop };
+    }
+}
+
+fn run_worker(mut computer: IntCodeComputer<ChannelInput, ChannelOutput>, stop: Arc<AtomicBool>) -> Result<MachineResult, IntcodeError> {
+    let state = loop {
+        computer.set_budget(Budget::steps(CHUNK));
+        match computer.run() {
+            Ok(RunState::BudgetExhausted) if !stop.load(Ordering::SeqCst) => continue,
+            Ok(RunState::Halted) => {
+                stop.store(true, Ordering::SeqCst);
+                break RunState::Halted;
+            },
+            Ok(state) => break state,
+            Err(error) => {
+                stop.store(true, Ordering::SeqCst);
+                return Err(error);
+            },
+        }
+    };
+
+    return Ok(MachineResult {
+        state,
+        intcode: computer.intcode,
+        outputs: computer.outputs.log,
+    });
+}
+
+pub struct RuntimeHandle {
+    threads: Vec<JoinHandle<Result<MachineResult, IntcodeError>>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl RuntimeHandle {
+    /// Asks every machine to finish at its next input or within a few
+    /// thousand instructions.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+
+    /// Waits for every machine. Returns the fault of the lowest numbered
+    /// machine that faulted, if any did.
+    pub fn join(self) -> Result<Vec<MachineResult>, NetworkError> {
+        let mut results = vec![];
+        let mut fault = None;
+        for (node, thread) in self.threads.into_iter().enumerate() {
+            match thread.join() {
+                Ok(Ok(result)) => results.push(result),
+                Ok(Err(error)) => {
+                    fault = fault.or(Some(NetworkError { node, error }));
+                },
+                Err(payload) => panic::resume_unwind(payload),
+            }
+        }
+
+        return match fault {
+            Some(fault) => Err(fault),
+            None => Ok(results),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::error::ErrorReason;
+    use super::super::memory::Memory;
+
+    #[test]
+    fn test_ring() {
+        // day 7 part 2 example, 9,8,7,6,5 gives 139629729
+        let intcode = vec![3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5];
+        let mut runtime = Runtime::new();
+        runtime.add_machine(&intcode, vec![9, 0]);
+        for phase in &[8, 7, 6, 5] {
+            runtime.add_machine(&intcode, vec![*phase]);
+        }
+        for node in 0..5 {
+            runtime.connect(node, (node + 1) % 5);
+        }
+
+        let results = runtime.start().join().unwrap();
+
+        assert_eq!(results[4].outputs.last(), Some(&139629729));
+        assert!(results.iter().all(|result| result.state == RunState::Halted));
+    }
+
+    #[test]
+    fn test_final_memory() {
+        // stores its input doubled at address 9
+        let intcode = vec![3,9,1002,9,2,9,4,9,99,0];
+        let mut runtime = Runtime::new();
+        let first = runtime.add_machine(&intcode, vec![21]);
+        let second = runtime.add_machine(&intcode, vec![]);
+        runtime.connect(first, second);
+
+        let results = runtime.start().join().unwrap();
+
+        assert_eq!(results[0].intcode.get(9), 42);
+        assert_eq!(results[1].intcode.get(9), 84);
+        assert_eq!(results[1].outputs, vec![84]);
+    }
+
+    #[test]
+    fn test_closed_input_stops_machine() {
+        let mut runtime = Runtime::new();
+        let first = runtime.add_machine(&[99], vec![]);
+        let second = runtime.add_machine(&[3,0,3,0,99], vec![1]);
+        runtime.connect(first, second);
+
+        let results = runtime.start().join().unwrap();
+
+        assert_eq!(results[1].state, RunState::AwaitingInput);
+        assert_eq!(results[1].intcode.get(0), 1);
+    }
+
+    #[test]
+    fn test_halt_stops_everything() {
+        let mut runtime = Runtime::new();
+        runtime.add_machine(&[99], vec![]);
+        // loops forever
+        runtime.add_machine(&[1105,1,0], vec![]);
+        // feeds itself forever
+        let feeding = runtime.add_machine(&[3,9,4,9,1105,1,0], vec![1]);
+        runtime.connect(feeding, feeding);
+
+        let results = runtime.start().join().unwrap();
+
+        assert_eq!(results[0].state, RunState::Halted);
+        assert_eq!(results[1].state, RunState::BudgetExhausted);
+        assert_ne!(results[2].state, RunState::Halted);
+    }
+
+    #[test]
+    fn test_fault_stops_everything() {
+        let mut runtime = Runtime::new();
+        // waits for input from itself, forever
+        let waiting = runtime.add_machine(&[3,0,1105,1,0], vec![]);
+        runtime.connect(waiting, waiting);
+        // loops forever
+        runtime.add_machine(&[1105,1,0], vec![]);
+        runtime.add_machine(&[42], vec![]);
+
+        let error = runtime.start().join().unwrap_err();
+
+        assert_eq!(error.node, 2);
+        assert_eq!(error.error.reason, ErrorReason::UnknownOpcode);
+    }
+
+    #[test]
+    fn test_stop() {
+        let mut runtime = Runtime::new();
+        runtime.add_machine(&[1105,1,0], vec![]);
+
+        let handle = runtime.start();
+        handle.stop();
+        let results = handle.join().unwrap();
+
+        assert_eq!(results[0].state, RunState::BudgetExhausted);
+    }
+}
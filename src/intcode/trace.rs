@@ -0,0 +1,235 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct MemoryWrite<C = i64> {
+    pub address: usize,
+    pub old: C,
+    pub new: C,
+}
+
+/// Everything one executed instruction did to the machine.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TraceEvent<C = i64> {
+    pub ptr: usize,
+    pub opcode: i64,
+    pub mnemonic: &'static str,
+    /// The raw parameter words following the opcode.
+    pub parameters: Vec<C>,
+    /// Values of the parameters the instruction read, after resolving modes.
+    pub operands: Vec<C>,
+    /// Addresses read through position or relative mode parameters.
+    pub reads: Vec<usize>,
+    pub writes: Vec<MemoryWrite<C>>,
+    /// Old and new relative base, if the instruction changed it.
+    pub relative_base: Option<(i64, i64)>,
+}
+
+impl<C: fmt::Display> TraceEvent<C> {
+    pub fn to_json(&self) -> String {
+        let writes: Vec<String> = self.writes.iter()
+            .map(|write| format!("{{\"address\":{},\"old\":{},\"new\":{}}}", write.address, write.old, write.new))
+            .collect();
+        let relative_base = match self.relative_base {
+            Some((old, new)) => format!("{{\"old\":{},\"new\":{}}}", old, new),
+            None => "null".to_owned(),
+        };
+
+        return format!(
+            "{{\"ptr\":{},\"opcode\":{},\"mnemonic\":{},\"parameters\":{},\"operands\":{},\"reads\":{},\"writes\":[{}],\"relative_base\":{}}}",
+            self.ptr, self.opcode, json_string(self.mnemonic), json_list(&self.parameters), json_list(&self.operands),
+            json_list(&self.reads), writes.join(","), relative_base);
+    }
+}
+
+/// A JSON string literal, since registered mnemonics can hold any character.
+fn json_string(text: &str) -> String {
+    let mut json = String::from("\"");
+    for character in text.chars() {
+        match character {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            control if (control as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", control as u32)),
+            character => json.push(character),
+        }
+    }
+    json.push('"');
+    return json;
+}
+
+fn json_list<T: ToString>(values: &[T]) -> String {
+    let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+    return format!("[{}]", values.join(","));
+}
+
+/// Receives a `TraceEvent` after every instruction the machine executes.
+pub trait TraceSink<C = i64> {
+    fn record(&mut self, event: &TraceEvent<C>);
+}
+
+/// Lets the caller keep a handle on a sink after giving it to a machine.
+impl<C, T: TraceSink<C>> TraceSink<C> for Arc<Mutex<T>> {
+    fn record(&mut self, event: &TraceEvent<C>) {
+        self.lock().unwrap().record(event);
+    }
+}
+
+/// Writes one JSON object per line. The first write error stops the trace and
+/// is returned by `finish`.
+pub struct JsonLinesSink<W: Write> {
+    writer: W,
+    error: Option<io::Error>,
+}
+
+impl JsonLinesSink<BufWriter<File>> {
+    pub fn create(path: &str) -> io::Result<JsonLinesSink<BufWriter<File>>> {
+        return Ok(JsonLinesSink::new(BufWriter::new(File::create(path)?)));
+    }
+}
+
+impl<W: Write> JsonLinesSink<W> {
+    pub fn new(writer: W) -> JsonLinesSink<W> {
+        return JsonLinesSink { writer, error: None };
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        self.writer.flush()?;
+        return Ok(self.writer);
+    }
+}
+
+impl<C: fmt::Display, W: Write> TraceSink<C> for JsonLinesSink<W> {
+    fn record(&mut self, event: &TraceEvent<C>) {
+        if self.error.is_some() {
+            return;
+        }
+        if let Err(error) = writeln!(self.writer, "{}", event.to_json()) {
+            self.error = Some(error);
+        }
+    }
+}
+
+/// Keeps the most recent `capacity` events.
+pub struct RingBufferSink<C = i64> {
+    capacity: usize,
+    events: VecDeque<TraceEvent<C>>,
+}
+
+impl<C> RingBufferSink<C> {
+    pub fn new(capacity: usize) -> RingBufferSink<C> {
+        return RingBufferSink {
+            capacity,
+            events: VecDeque::with_capacity(capacity),
+        };
+    }
+
+    /// Oldest first.
+    pub fn events(&self) -> impl Iterator<Item = &TraceEvent<C>> {
+        return self.events.iter();
+    }
+}
+
+impl<C: Clone> TraceSink<C> for RingBufferSink<C> {
+    fn record(&mut self, event: &TraceEvent<C>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::computer::IntCodeComputer;
+    use super::super::opcodes::{Effect, OpcodeTable};
+
+    #[test]
+    fn test_events() {
+        let intcode = vec![109,2,22201,1,2,-1,99];
+        let expected = vec![
+            TraceEvent {
+                ptr: 0, opcode: 109, mnemonic: "ARB", parameters: vec![2], operands: vec![2],
+                reads: vec![], writes: vec![], relative_base: Some((0, 2)),
+            },
+            TraceEvent {
+                ptr: 2, opcode: 22201, mnemonic: "ADD", parameters: vec![1,2,-1], operands: vec![1, 2],
+                reads: vec![3, 4], writes: vec![MemoryWrite { address: 1, old: 2, new: 3 }], relative_base: None,
+            },
+            TraceEvent {
+                ptr: 6, opcode: 99, mnemonic: "HLT", parameters: vec![], operands: vec![],
+                reads: vec![], writes: vec![], relative_base: None,
+            },
+        ];
+        let sink = Arc::new(Mutex::new(RingBufferSink::new(10)));
+
+        let mut computer = IntCodeComputer::new(&intcode, vec![]);
+        computer.set_tracer(Box::new(sink.clone()));
+        computer.run().unwrap();
+
+        let events: Vec<TraceEvent> = sink.lock().unwrap().events().cloned().collect();
+        assert_eq!(events, expected);
+    }
+
+    #[test]
+    fn test_ring_buffer_keeps_latest() {
+        let intcode = vec![1101,1,1,9,1101,2,2,9,99,0];
+        let sink = Arc::new(Mutex::new(RingBufferSink::new(2)));
+
+        let mut computer = IntCodeComputer::new(&intcode, vec![]);
+        computer.set_tracer(Box::new(sink.clone()));
+        computer.run().unwrap();
+
+        let ptrs: Vec<usize> = sink.lock().unwrap().events().map(|event| event.ptr).collect();
+        assert_eq!(ptrs, vec![4, 8]);
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let intcode = vec![3,5,4,5,99,0];
+        let expected = "\
+{\"ptr\":0,\"opcode\":3,\"mnemonic\":\"IN\",\"parameters\":[5],\"operands\":[],\"reads\":[],\"writes\":[{\"address\":5,\"old\":0,\"new\":7}],\"relative_base\":null}
+{\"ptr\":2,\"opcode\":4,\"mnemonic\":\"OUT\",\"parameters\":[5],\"operands\":[7],\"reads\":[5],\"writes\":[],\"relative_base\":null}
+{\"ptr\":4,\"opcode\":99,\"mnemonic\":\"HLT\",\"parameters\":[],\"operands\":[],\"reads\":[],\"writes\":[],\"relative_base\":null}
+";
+
+        let sink = Arc::new(Mutex::new(JsonLinesSink::new(vec![])));
+        let mut computer = IntCodeComputer::new(&intcode, vec![7]);
+        computer.set_tracer(Box::new(sink.clone()));
+        computer.run().unwrap();
+        drop(computer);
+
+        let sink = Arc::try_unwrap(sink).ok().unwrap().into_inner().unwrap();
+        let written = sink.finish().unwrap();
+        assert_eq!(String::from_utf8(written).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_json_escapes_mnemonics() {
+        let mut opcodes = OpcodeTable::default();
+        opcodes.register(50, "SAY\"HI\\", 0, None, |_: &[i64]| Ok(Effect::Halt));
+        let sink = Arc::new(Mutex::new(RingBufferSink::new(1)));
+
+        let mut computer = IntCodeComputer::new(&[50], vec![]);
+        computer.set_opcodes(opcodes);
+        computer.set_tracer(Box::new(sink.clone()));
+        computer.run().unwrap();
+
+        let json = sink.lock().unwrap().events().next().unwrap().to_json();
+        assert!(json.contains(r#""mnemonic":"SAY\"HI\\","#), "{}", json);
+        assert_eq!(json_string("a\u{1}b\n"), r#""a\u0001b\n""#);
+    }
+}
@@ -0,0 +1,275 @@
+use std::fs;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use aoc2019::intcode;
+use aoc2019::intcode::{Budget, IntCodeComputer, RunState};
+use aoc2019::intcode::cfg::control_flow_graph;
+use aoc2019::intcode::compiled::CompiledComputer;
+use aoc2019::intcode::coverage::Coverage;
+use aoc2019::intcode::debugger::Debugger;
+use aoc2019::intcode::differential;
+use aoc2019::intcode::fuzz;
+use aoc2019::intcode::host;
+use aoc2019::intcode::profiler::Profiler;
+use aoc2019::intcode::snapshot::Snapshot;
+use aoc2019::intcode::trace::JsonLinesSink;
+
+use super::file_loader;
+
+/// How long a program may run from the command line before it is stopped.
+const TIME_BUDGET: Duration = Duration::from_secs(30);
+
+/// How many instructions each fuzzed program may run.
+const FUZZ_STEPS: u64 = 10_000;
+
+/// Where the fuzzer saves minimised failures, which the tests then replay.
+const FUZZ_REGRESSIONS: &str = "resources/fuzz";
+
+/// How many instructions `diff` runs the engines in lockstep before giving up.
+const DIFF_STEPS: u64 = 10_000_000;
+
+fn load_program(path: &str) -> Vec<i64> {
+    let contents: String = fs::read_to_string(path)
+        .expect("Something went wrong reading the file");
+
+    return intcode::parse(&contents);
+}
+
+pub fn disasm(args: &[String]) {
+    let path = args.first().expect("Should provide an intcode file to disassemble");
+    let program = load_program(path);
+
+    print!("{}", intcode::listing(&program));
+}
+
+/// Prints the control-flow graph as Graphviz source, or writes it to a file.
+pub fn cfg(args: &[String]) {
+    let path = args.first().expect("Should provide an intcode file to graph");
+    let dot = control_flow_graph(&load_program(path)).to_dot();
+
+    match args.get(1) {
+        Some(output) => fs::write(output, dot).expect("Could not write the graph"),
+        None => print!("{}", dot),
+    }
+}
+
+pub fn debug(args: &[String]) {
+    let day = args.first().expect("Should provide the day whose program to debug");
+    let input = file_loader::load_file(&format!("{}.input", day));
+    let program = intcode::parse(&input);
+
+    let mut debugger = Debugger::new(IntCodeComputer::new(&program, vec![]));
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    debugger.repl(stdin.lock(), &mut stdout.lock()).expect("Debugger could not use the terminal");
+}
+
+pub fn trace(args: &[String]) {
+    if args.len() < 2 {
+        panic!("Should provide an intcode file, a trace file and optional inputs");
+    }
+    let program = load_program(&args[0]);
+    let inputs = parse_inputs(&args[2..]);
+
+    let sink = JsonLinesSink::create(&args[1]).expect("Could not create the trace file");
+    let mut computer = IntCodeComputer::new(&program, inputs);
+    computer.set_tracer(Box::new(sink));
+    computer.set_budget(Budget::time(TIME_BUDGET));
+    let result = computer.run();
+    drop(computer.take_tracer());
+
+    println!("Outputs: {:?}", computer.outputs);
+    match result {
+        Ok(state) => println!("Stopped: {:?}", state),
+        Err(error) => println!("Fault: {}", error),
+    }
+}
+
+/// Runs a program and prints its coverage over the memory it finished with.
+pub fn coverage(args: &[String]) {
+    let path = args.first().expect("Should provide an intcode file and optional inputs");
+    let program = load_program(path);
+
+    let coverage = Arc::new(Mutex::new(Coverage::new()));
+    let mut computer = IntCodeComputer::new(&program, parse_inputs(&args[1..]));
+    computer.set_tracer(Box::new(coverage.clone()));
+    computer.set_budget(Budget::time(TIME_BUDGET));
+    let result = computer.run();
+
+    let memory = computer.intcode.segments()[0].1.to_vec();
+    print!("{}", coverage.lock().unwrap().report(&memory));
+    println!("Outputs: {:?}", computer.outputs);
+    match result {
+        Ok(state) => println!("Stopped: {:?}", state),
+        Err(error) => println!("Fault: {}", error),
+    }
+}
+
+/// Runs a program, prints where it spent its time and writes folded call
+/// stacks for a flamegraph.
+pub fn profile(args: &[String]) {
+    if args.len() < 2 {
+        panic!("Should provide an intcode file, a file for folded stacks and optional inputs");
+    }
+    let program = load_program(&args[0]);
+
+    let profiler = Arc::new(Mutex::new(Profiler::new()));
+    let mut computer = IntCodeComputer::new(&program, parse_inputs(&args[2..]));
+    computer.set_tracer(Box::new(profiler.clone()));
+    computer.set_budget(Budget::time(TIME_BUDGET));
+    let result = computer.run();
+
+    let profiler = profiler.lock().unwrap();
+    let memory = computer.intcode.segments()[0].1.to_vec();
+    print!("{}", profiler.report(&memory, 20));
+    fs::write(&args[1], profiler.folded()).expect("Could not write the folded stacks");
+    match result {
+        Ok(state) => println!("Stopped: {:?}", state),
+        Err(error) => println!("Fault: {}", error),
+    }
+}
+
+/// Runs a program on every engine in lockstep and reports the first place
+/// they disagree.
+pub fn diff(args: &[String]) {
+    let path = args.first().expect("Should provide an intcode file and optional inputs");
+    let program = load_program(path);
+
+    let mut engines = differential::engines(&program, &parse_inputs(&args[1..]));
+    let names: Vec<String> = engines.iter().map(|engine| engine.name()).collect();
+    match differential::compare(&mut engines, DIFF_STEPS) {
+        Ok(agreement) => {
+            println!("{} engines agree for {} steps: {}", engines.len(), agreement.steps, names.join(", "));
+            match agreement.result {
+                Some(result) => println!("Stopped: {:?}", result),
+                None => println!("Stopped: step limit"),
+            }
+        },
+        Err(divergence) => print!("{}", divergence),
+    }
+}
+
+/// Runs a program with the standard host services: a clock, random numbers,
+/// logging to stderr, and reading files under resources/.
+pub fn host(args: &[String]) {
+    let path = args.first().expect("Should provide an intcode file and optional inputs");
+    let program = load_program(path);
+
+    let mut computer = IntCodeComputer::new(&program, parse_inputs(&args[1..]));
+    host::register_standard(&mut computer, PathBuf::from("resources"), 0);
+    computer.set_budget(Budget::time(TIME_BUDGET));
+    let result = computer.run();
+
+    println!("Outputs: {:?}", computer.outputs);
+    match result {
+        Ok(state) => println!("Stopped: {:?}", state),
+        Err(error) => println!("Fault: {}", error),
+    }
+}
+
+/// Checks random programs against the machine's invariants and saves each
+/// failure, minimised, as a regression test.
+pub fn fuzz(args: &[String]) {
+    let cases = args.first().map(|cases| cases.parse().expect("Number of cases should be a number")).unwrap_or(10_000);
+    let seed = match args.get(1) {
+        Some(seed) => seed.parse().expect("Seed should be a number"),
+        None => SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+    };
+
+    println!("Fuzzing {} cases from seed {}", cases, seed);
+    let failures = fuzz::fuzz(seed, cases, FUZZ_STEPS);
+    for (case, failure) in &failures {
+        let path = case.save(Path::new(FUZZ_REGRESSIONS)).expect("Could not save the failing case");
+        println!("{}\n  saved to {}", failure, path.display());
+    }
+    println!("{} failures", failures.len());
+}
+
+/// Times a day's program on each backend, including loading and translation.
+/// Defaults to day 9 part 2, which runs a few hundred thousand instructions.
+pub fn bench(args: &[String]) {
+    let day = args.first().map(|day| day.as_str()).unwrap_or("9");
+    let inputs = match args.get(1) {
+        Some(input) => vec![input.parse::<i64>().expect("Input should be a number")],
+        None => vec![2],
+    };
+    let runs = args.get(2).map(|runs| runs.parse::<u32>().expect("Runs should be a number")).unwrap_or(5);
+    let program = intcode::parse(&file_loader::load_file(&format!("{}.input", day)));
+
+    println!("{}.input with inputs {:?}, best of {} runs", day, inputs, runs);
+    let mut baseline = None;
+    for backend in &["decode cache off", "decode cache on", "compiled"] {
+        let mut fastest = Duration::MAX;
+        let mut executed = 0;
+        for _ in 0..runs {
+            let start = Instant::now();
+            executed = if *backend == "compiled" {
+                let mut computer = CompiledComputer::new(&program, inputs.clone());
+                computer.run().expect("Benchmark program faulted");
+                computer.executed()
+            } else {
+                let mut computer = IntCodeComputer::new(&program, inputs.clone());
+                computer.set_decode_cache(*backend == "decode cache on");
+                computer.run().expect("Benchmark program faulted");
+                computer.executed()
+            };
+            fastest = fastest.min(start.elapsed());
+        }
+        let baseline = *baseline.get_or_insert(fastest);
+        println!("{:<17} {:>10.2?}  {:>6.1} ns/instruction  {:.2}x  ({} instructions)",
+            backend, fastest, fastest.as_nanos() as f64 / executed as f64,
+            baseline.as_secs_f64() / fastest.as_secs_f64(), executed);
+    }
+}
+
+fn parse_inputs(args: &[String]) -> Vec<i64> {
+    return args.iter()
+        .map(|input| input.parse::<i64>().expect("Inputs should be numbers"))
+        .collect();
+}
+
+/// Runs a program from the start, saving its state to the snapshot file if it
+/// stops to wait for input.
+pub fn start(args: &[String]) {
+    if args.len() < 2 {
+        panic!("Should provide an intcode file, a snapshot file and optional inputs");
+    }
+    let program = load_program(&args[0]);
+
+    let computer = IntCodeComputer::new(&program, parse_inputs(&args[2..]));
+    run_and_save(computer, &args[1]);
+}
+
+/// Continues a program saved by `start` or a previous `resume`.
+pub fn resume(args: &[String]) {
+    let path = args.first().expect("Should provide a snapshot file and optional inputs");
+    let snapshot = Snapshot::load(path).expect("Could not load the snapshot");
+
+    let mut computer = IntCodeComputer::new(&[], parse_inputs(&args[1..]));
+    computer.restore(&snapshot);
+    run_and_save(computer, path);
+}
+
+fn run_and_save(mut computer: IntCodeComputer, path: &str) {
+    computer.set_budget(Budget::time(TIME_BUDGET));
+    let result = computer.run();
+    for output in &computer.outputs {
+        println!("{}", output);
+    }
+
+    match result {
+        Ok(RunState::AwaitingInput) => {
+            computer.snapshot().save(path).expect("Could not save the snapshot");
+            println!("Waiting for input. State saved to {}", path);
+        },
+        Ok(RunState::BudgetExhausted) => {
+            computer.snapshot().save(path).expect("Could not save the snapshot");
+            println!("Stopped after {} instructions. State saved to {}", computer.executed(), path);
+        },
+        Ok(_) => println!("Halted"),
+        Err(error) => println!("Fault: {}", error),
+    }
+}
@@ -1,7 +1,10 @@
+#![allow(clippy::needless_return)]
+
 use std::env;
 use std::fs;
 
 mod file_loader;
+mod intcode_tools;
 mod day_two;
 mod day_three;
 mod day_four;
@@ -14,6 +17,22 @@ mod day_10;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
+    match args[1].as_str() {
+        "disasm" => return intcode_tools::disasm(&args[2..]),
+        "cfg" => return intcode_tools::cfg(&args[2..]),
+        "coverage" => return intcode_tools::coverage(&args[2..]),
+        "debug" => return intcode_tools::debug(&args[2..]),
+        "diff" => return intcode_tools::diff(&args[2..]),
+        "fuzz" => return intcode_tools::fuzz(&args[2..]),
+        "host" => return intcode_tools::host(&args[2..]),
+        "trace" => return intcode_tools::trace(&args[2..]),
+        "start" => return intcode_tools::start(&args[2..]),
+        "resume" => return intcode_tools::resume(&args[2..]),
+        "profile" => return intcode_tools::profile(&args[2..]),
+        "bench" => return intcode_tools::bench(&args[2..]),
+        _ => (),
+    }
+
     let day: i32 = args[1].parse::<i32>().expect("Should provide day as first argument");
     let part: i32 = args[2].parse::<i32>().expect("Should provide part as second argument");
     let test: bool = false;
@@ -52,12 +71,12 @@ fn day_one(part: i32, test: bool) {
     let result: i32 = if part == 1 {
         contents.lines()
             .map(|l| l.parse::<i32>().unwrap())
-            .map(|i| calculate_fuel(i))
+            .map(calculate_fuel)
             .sum()
     } else {
         let calculated: Vec<i32> = contents.lines()
             .map(|l| l.parse::<i32>().unwrap())
-            .map(|i| fuel_from_mass(i))
+            .map(fuel_from_mass)
             .collect();
         println!("Calculated values: {:?}", calculated);
         calculated.iter().sum()
@@ -74,7 +93,7 @@ fn fuel_from_mass(module_mass: i32) -> i32 {
         if result <= 0 {
             break;
         }
-        fuel = fuel + result;
+        fuel += result;
         mass = result;
         println!("result: {}, fuel: {}, mass: {}", result, fuel, mass);
     }